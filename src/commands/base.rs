@@ -1,7 +1,10 @@
 use crate::csctrl::csctrl::Csctrl;
 
+/// Output of a command on success, reason it failed otherwise
+pub type CommandResult = Result<String, String>;
+
 pub trait Command {
-    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String);
+    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) -> CommandResult;
     fn name(&self) -> String;
     fn description(&self) -> String;
     fn variables(&self) -> String;
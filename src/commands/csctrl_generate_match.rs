@@ -1,10 +1,10 @@
 use std::path::Path;
-use crate::commands::base::Command;
+use crate::commands::base::{Command, CommandResult};
 use crate::csctrl::csctrl::Csctrl;
 
 pub struct CsctrlGenerateMatch;
 impl Command for CsctrlGenerateMatch {
-    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) {
+    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) -> CommandResult {
         let match_name_vec: Vec<&str> = arguments.split(" ").collect();
         let match_name = match_name_vec[0].to_owned() + ".json";
         tracing::trace!("Attempting to create match setup file with name '{}'", match_name);
@@ -15,23 +15,16 @@ impl Command for CsctrlGenerateMatch {
         csctrl_binary_path.push(&match_name);
 
         if Path::new(&csctrl_binary_path).exists() {
-            tracing::error!("Match setup file '{}' already exists", &match_name);
-            return;
+            return Err(format!("Match setup file '{}' already exists", &match_name));
         }
 
-        let match_setup = crate::csctrl::types::MatchSetup {
-            team_a_name: "".to_string(),
-            team_b_name: "".to_string(),
-            knife_round: false,
-            cfg_filename: "".to_string(),
-            player_amount: 0,
-        };
+        let match_setup = crate::csctrl::types::MatchSetup::default();
 
         let file = std::fs::OpenOptions::new().write(true).truncate(true).create(true).open(&csctrl_binary_path).unwrap();
-        match serde_json::to_writer_pretty(file, &match_setup) {
-            Err(_) => { tracing::error!("Can't write match setup to file '{}'", csctrl_binary_path.to_str().unwrap()) }
-            _ => {}
-        }
+        return match serde_json::to_writer_pretty(file, &match_setup) {
+            Err(_) => { Err(format!("Can't write match setup to file '{}'", csctrl_binary_path.to_str().unwrap())) }
+            _ => { Ok(format!("Created match setup file '{}'", match_name)) }
+        };
     }
 
     fn name(&self) -> String { "csctrl.generate.match".to_string() }
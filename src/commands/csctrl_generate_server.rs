@@ -1,18 +1,19 @@
+use crate::commands::base::CommandResult;
 use crate::csctrl::csctrl::Csctrl;
 use crate::csctrl::types::CsctrlServerSetup;
 
 pub struct CsctrlGenerateServer;
 
 impl crate::commands::base::Command for CsctrlGenerateServer {
-    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) {
+    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) -> CommandResult {
         let split_arguments: Vec<&str> = arguments.split(" ").collect();
 
         if split_arguments.len() != 3 {
-            tracing::error!("{} expects 3 arguments but was provided {}", self.name(), split_arguments.len());
-            return;
+            return Err(format!("{} expects 3 arguments but was provided {}", self.name(), split_arguments.len()));
         }
 
         let server_name = split_arguments[0].to_string();
+        let result_message = format!("Added server '{}'", server_name);
         let server_address = split_arguments[1].to_string();
         let server_rcon_password = split_arguments[2].to_string();
 
@@ -21,9 +22,11 @@ impl crate::commands::base::Command for CsctrlGenerateServer {
             address: server_address,
             rcon_password: server_rcon_password,
             match_setup: "default".to_string(),
+            webhooks: vec![],
         });
 
         csctrl.write_config();
+        return Ok(result_message);
     }
 
     fn name(&self) -> String {
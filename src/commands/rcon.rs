@@ -1,21 +1,21 @@
-use crate::commands::base::{Command};
+use crate::commands::base::{Command, CommandResult};
 use crate::csctrl::csctrl::Csctrl;
+use crate::csctrl::types::CsctrlServerRequest;
 
 pub struct Rcon;
 impl Command for Rcon {
-    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) {
-        let found_server = match csctrl.servers.get(&target_address) {
-            Some(server) => { server }
-            None => {
-                tracing::error!("No server with address '{}' to run rcon on", target_address);
-                return;
-            }
-        };
+    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) -> CommandResult {
+        if !csctrl.servers.contains_key(&target_address) {
+            return Err(format!("No server with address '{}' to run rcon on", target_address));
+        }
 
-        match found_server.sender.send(format!("rcon {}", arguments)) {
-            Ok(_) => {}
-            Err(error) => { tracing::error!("Can't send message to thread belonging to server '{}'. Error: {}", target_address, error); }
+        // The response arrives later from the server thread and is matched back to the request by its id
+        let request_id = csctrl.register_rcon_request(&target_address, &arguments);
+        if !csctrl.send_message_to_server_thread(&target_address, CsctrlServerRequest::Rcon { request_id, command: arguments }) {
+            csctrl.cancel_rcon_request(request_id);
+            return Err(format!("Can't send rcon command to server '{}'", target_address));
         }
+        return Ok(format!("Sent rcon command to server '{}'", target_address));
     }
 
     fn name(&self) -> String {
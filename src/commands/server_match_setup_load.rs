@@ -1,37 +1,37 @@
-use tokio::sync::mpsc::error::SendError;
-use crate::csctrl::csctrl::Csctrl;
-use crate::csctrl::types::MatchSetup;
-use crate::system::utilities::get_csctrl_config_file_path;
+use crate::commands::base::CommandResult;
+use crate::csctrl::csctrl::{Csctrl, get_data};
+use crate::csctrl::types::{CsctrlMatchStatus, MatchSetup};
 
 pub struct ServerMatchSetupLoad;
 
 impl crate::commands::base::Command for ServerMatchSetupLoad {
-    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) {
-        let found_server = csctrl.servers.get_mut(&target_address);
-        if found_server.is_none() {
-            tracing::error!("Can't find a server with address '{}'", &target_address);
-            return;
-        }
-
+    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) -> CommandResult {
         let split_arguments: Vec<&str> = arguments.split(" ").collect();
-        let mut match_setup_path = get_csctrl_config_file_path();
-        match_setup_path.pop();
-        match_setup_path.push(format!("matches/{}.json", &split_arguments[0]));
-        if !match_setup_path.exists() {
-            tracing::error!("Match setup file '{}' does not exist", &split_arguments[0]);
-            return;
-        }
-
-        let setup_as_string = std::fs::read_to_string(match_setup_path);
-        if setup_as_string.is_err() {
-            tracing::error!("Error reading match setup file '{}'", &split_arguments[0]);
-            return;
+        let match_setup = match MatchSetup::load_match_setup(split_arguments[0]) {
+            Ok(valid_json_setup) => { valid_json_setup }
+            Err(error) => { return Err(error); }
+        };
+
+        let mut data_write_lock = get_data().write().unwrap();
+        let server_data = match data_write_lock.servers.get_mut(&target_address) {
+            Some(server_data) => { server_data }
+            None => { return Err(format!("Can't find a server with address '{}'", &target_address)); }
+        };
+
+        if server_data.status != CsctrlMatchStatus::NoHook && server_data.status != CsctrlMatchStatus::PreMatchWarmup && server_data.status != CsctrlMatchStatus::Veto {
+            return Err(format!("Can't load a match setup on server '{}' while a match is being played", &target_address));
         }
 
-        match found_server.unwrap().sender.send(setup_as_string.unwrap()) {
-            Ok(_) => {}
-            Err(error) => { tracing::error!("Can't send message to thread belonging to server '{}'. Error: {}", target_address, error); }
+        server_data.match_setup = match_setup;
+        server_data.series = Default::default();
+        server_data.veto = Default::default();
+        // A veto of the previous setup can't go on without its map pool, so the match has to be started again
+        if server_data.status == CsctrlMatchStatus::Veto {
+            server_data.status = CsctrlMatchStatus::NoHook;
         }
+        server_data.config.match_setup = split_arguments[0].to_string();
+        csctrl.set_data_dirty();
+        return Ok(format!("Loaded match setup '{}' on server '{}'", split_arguments[0], &target_address));
     }
 
     fn name(&self) -> String {
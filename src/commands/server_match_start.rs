@@ -1,21 +1,17 @@
-use tokio::sync::mpsc::error::SendError;
+use crate::commands::base::CommandResult;
 use crate::csctrl::csctrl::{Csctrl, get_data};
-use crate::system::utilities::get_csctrl_config_file_path;
 
 pub struct ServerMatchStart;
 
 impl crate::commands::base::Command for ServerMatchStart {
-    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) {
-        let found_server = csctrl.servers.get_mut(&target_address);
-        if found_server.is_none() {
-            tracing::error!("Can't find a server with address '{}'", &target_address);
-            return;
-        }
+    fn exec(&self, csctrl: &mut Csctrl, target_address: String, arguments: String) -> CommandResult {
+        let mut data_write_lock = get_data().write().unwrap();
+        let server_data = match data_write_lock.servers.get_mut(&target_address) {
+            Some(server_data) => { server_data }
+            None => { return Err(format!("Can't find a server with address '{}'", &target_address)); }
+        };
 
-        match found_server.unwrap().sender.send(self.name()) {
-            Ok(_) => {}
-            Err(error) => { tracing::error!("Can't send message to thread belonging to server '{}'. Error: {}", target_address, error); }
-        }
+        return csctrl.start_server_match(server_data);
     }
 
     fn name(&self) -> String {
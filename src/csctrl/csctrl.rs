@@ -1,11 +1,14 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::ops::Deref;
 use std::string::ToString;
-use std::sync::{OnceLock, RwLock};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::Duration;
 use regex::Regex;
+use tracing::Instrument;
 use tokio::sync::mpsc::error::TryRecvError;
-use crate::{csctrl, system};
-use crate::commands::base::Command;
+use clap::Parser;
+use crate::{csctrl, system, ClapParser};
+use crate::commands::base::{Command, CommandResult};
 use crate::commands::csctrl_generate_match::CsctrlGenerateMatch;
 use crate::commands::csctrl_generate_server::CsctrlGenerateServer;
 use crate::commands::rcon::Rcon;
@@ -13,12 +16,22 @@ use crate::commands::server_match_setup_load::ServerMatchSetupLoad;
 use crate::commands::server_match_start::ServerMatchStart;
 use crate::commands::terminal_server_select::TerminalServerSelect;
 use crate::csctrl::server::CsctrlServer;
-use crate::csctrl::types::{CsctrlDataParent, CsctrlDataServer, CsctrlDataTeam, CsctrlLogType, CsctrlMatchStatus, CsctrlServerContainer, CsctrlServerSetup, CsctrlStaticData, MatchSetup};
+use crate::csctrl::server_status::parse_server_status;
+use crate::csctrl::types::{CsctrlEvent, CsctrlServerEvent, CsctrlConnectionState, CsctrlPauseType, CsctrlDataParent, CsctrlDataSeries, CsctrlDataVeto, CsctrlDataServer, CsctrlLogType, CsctrlMatchStatus, CsctrlMessage, CsctrlRconRequest, CsctrlServerReply, CsctrlServerRequest, CsctrlServerContainer, CsctrlServerSetup, CsctrlStaticData, MatchSetup};
 use crate::csctrl::types::CsctrlLogType::Invalid;
+use crate::system::utilities::get_unix_timestamp_secs;
 use crate::terminal::terminal::Terminal;
+use crate::webserver::webhooks::Webhooks;
 use crate::webserver::webserver::Webserver;
 
-pub const FORMAT_SEPARATOR: &str = "<csctrlseptarget>";
+const EVENT_BROADCAST_CAPACITY: usize = 1024;
+const HEALTH_CHECK_FAILURES_BEFORE_OFFLINE: u32 = 2;
+/// Drives ready checks, pause timeouts and terminal redraws like the blinking cursor
+const PERIODIC_INTERVAL_MILLIS: u64 = 500;
+/// How long server actors, the rest api and webhook deliveries get to finish once csctrl is asked to stop
+pub const SHUTDOWN_TIMEOUT_SECS: u64 = 5;
+/// Long enough for a command queued behind a match start countdown. Replies lost to an aborted server actor are given up on after it
+const RCON_REQUEST_TIMEOUT_SECS: u64 = 60;
 
 pub fn get_static_data() -> &'static RwLock<CsctrlStaticData> {
     static STATIC_DATA: OnceLock<RwLock<CsctrlStaticData>> = OnceLock::new();
@@ -27,14 +40,20 @@ pub fn get_static_data() -> &'static RwLock<CsctrlStaticData> {
     }))
 }
 
-pub fn get_command_messenger() -> &'static RwLock<VecDeque<String>> {
-    static COMMAND_MESSENGER: OnceLock<RwLock<VecDeque<String>>> = OnceLock::new();
-    COMMAND_MESSENGER.get_or_init(|| RwLock::new(VecDeque::new()))
+/// The receiver is taken once by the main loop
+type MessageChannel = (tokio::sync::mpsc::UnboundedSender<CsctrlMessage>, Mutex<Option<tokio::sync::mpsc::UnboundedReceiver<CsctrlMessage>>>);
+
+fn get_message_channel() -> &'static MessageChannel {
+    static MESSAGE_CHANNEL: OnceLock<MessageChannel> = OnceLock::new();
+    MESSAGE_CHANNEL.get_or_init(|| {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        return (sender, Mutex::new(Some(receiver)));
+    })
 }
 
-pub fn get_weblogs_messenger() -> &'static RwLock<VecDeque<String>> {
-    static WEBLOGS_MESSENGER: OnceLock<RwLock<VecDeque<String>>> = OnceLock::new();
-    WEBLOGS_MESSENGER.get_or_init(|| RwLock::new(VecDeque::new()))
+/// The terminal, the webserver and the server threads all reach the main thread through this sender
+pub fn get_message_sender() -> &'static tokio::sync::mpsc::UnboundedSender<CsctrlMessage> {
+    return &get_message_channel().0;
 }
 
 pub fn get_registered_commands() -> &'static RwLock<HashMap<String, Box<dyn Command + Sync + Send>>> {
@@ -47,30 +66,59 @@ pub fn get_data() -> &'static RwLock<CsctrlDataParent> {
     CSCTRL_READ_DATA.get_or_init(|| RwLock::new(CsctrlDataParent { servers: HashMap::new() }))
 }
 
+pub fn get_event_broadcaster() -> &'static tokio::sync::broadcast::Sender<CsctrlServerEvent> {
+    static EVENT_BROADCASTER: OnceLock<tokio::sync::broadcast::Sender<CsctrlServerEvent>> = OnceLock::new();
+    EVENT_BROADCASTER.get_or_init(|| tokio::sync::broadcast::channel(EVENT_BROADCAST_CAPACITY).0)
+}
+
+/// Sending only fails when nobody is subscribed to the event stream, which is fine to ignore
+pub fn broadcast_event(server_address: &str, event: CsctrlEvent) {
+    let _ = get_event_broadcaster().send(CsctrlServerEvent {
+        server_address: server_address.to_string(),
+        event,
+    });
+}
+
 pub struct Csctrl {
     requested_exit: bool,
+    is_headless: bool,
     pub csctrl_config: csctrl::types::CsctrlConfig,
     webserver: Webserver,
+    webhooks: Webhooks,
     pub terminal: Terminal,
     pub servers: HashMap<String, CsctrlServerContainer>,
-    server_threads_receiver: OnceLock<tokio::sync::mpsc::UnboundedReceiver<String>>,
-    server_threads_sender: OnceLock<tokio::sync::mpsc::UnboundedSender<String>>,
     is_data_dirty: bool,
-    log_regex_matchers: HashMap<CsctrlLogType, Regex>
+    /// Tried in order and the first match wins, so chat comes first and its text can't pass for another event
+    log_regex_matchers: Vec<(CsctrlLogType, Regex)>,
+    command_responder: Option<tokio::sync::oneshot::Sender<CommandResult>>,
+    pending_rcon_requests: HashMap<u64, CsctrlRconRequest>,
+    next_rcon_request_id: u64,
+    last_broadcast_states: HashMap<String, (CsctrlMatchStatus, u8, u8)>,
+    /// Handed back once run returns, so server actors still finishing a request during shutdown have someone to reply to
+    message_receiver: Option<tokio::sync::mpsc::UnboundedReceiver<CsctrlMessage>>,
 }
 
 impl Csctrl {
     pub fn csctrl() -> Csctrl {
+        return Self::create_csctrl(system::utilities::load_config(), ClapParser::parse().disable_terminal);
+    }
+
+    fn create_csctrl(csctrl_config: csctrl::types::CsctrlConfig, is_headless: bool) -> Csctrl {
         Self {
             requested_exit: false,
-            csctrl_config: system::utilities::load_config(),
+            is_headless,
+            csctrl_config,
             webserver: Webserver::webserver(),
+            webhooks: Webhooks::webhooks(),
             terminal: Terminal::terminal(),
             servers: HashMap::new(),
-            server_threads_receiver: OnceLock::new(),
-            server_threads_sender: OnceLock::new(),
             is_data_dirty: false,
-            log_regex_matchers: Default::default(),
+            log_regex_matchers: vec![],
+            command_responder: None,
+            pending_rcon_requests: HashMap::new(),
+            next_rcon_request_id: 0,
+            last_broadcast_states: HashMap::new(),
+            message_receiver: None,
         }
     }
 
@@ -80,41 +128,90 @@ impl Csctrl {
 
         let _ = self.register_commands();
         let _ = self.webserver.init(&self.csctrl_config);
+        let _ = self.webhooks.init(&self.csctrl_config);
         let _ = self.terminal.init();
 
-
-        let(sender, receiver) = tokio::sync::mpsc::unbounded_channel();
-        self.server_threads_receiver.get_or_init(|| receiver);
-        self.server_threads_sender.get_or_init(|| sender);
+        self.register_log_regex_matchers();
         self.reset_registered_servers();
     }
 
-    pub fn tick(&mut self) {
-        if *self.terminal.is_terminal_active() { self.terminal.tick(); }
-        else { self.requested_exit = true; }
+    /// Sleeps until a message, terminal input or the periodic timer wakes it up, so an idle csctrl doesn't use any CPU
+    pub async fn run(&mut self) {
+        let mut message_receiver = get_message_channel().1.lock().unwrap().take().expect("Csctrl can only run once");
+        let mut terminal_event_receiver = self.terminal.take_event_receiver();
+        let mut periodic_interval = tokio::time::interval(Duration::from_millis(PERIODIC_INTERVAL_MILLIS));
+        periodic_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let shutdown_signal = system::utilities::wait_for_shutdown_signal();
+        tokio::pin!(shutdown_signal);
+
+        while !self.requested_exit {
+            tokio::select! {
+                _ = &mut shutdown_signal => { self.requested_exit = true; }
+                Some(message) = message_receiver.recv() => { self.handle_message(message); }
+                Some(terminal_event) = async { terminal_event_receiver.as_mut()?.recv().await } => {
+                    self.terminal.handle_event(terminal_event);
+                }
+                _ = periodic_interval.tick() => {
+                    self.process_ready_checks();
+                    self.process_pause_timeouts();
+                    self.process_rcon_request_timeouts();
+                }
+            }
 
-        self.process_command_messenger();
-        self.process_weblog_messenger();
-        self.process_server_receiver_channel();
-        self.register_log_regex_matchers();
+            if self.is_data_dirty { self.handle_dirty_data(); }
+            if *self.terminal.is_terminal_active() { self.terminal.draw(); }
+            else if !self.is_headless { self.requested_exit = true; }
+        }
 
-        if self.is_data_dirty { self.handle_dirty_data(); }
+        self.message_receiver = Some(message_receiver);
     }
 
-    pub fn shutdown(&self) {
+    pub async fn shutdown(&mut self) {
         tracing::info!("Exiting CSCTRL");
-        let _ = &self.terminal.shutdown();
-        let _ = &self.webserver.shutdown();
+        self.terminal.shutdown();
+
+        // Dropping the senders tells every server actor to stop once its current request is done
+        let server_tasks: Vec<(String, tokio::task::JoinHandle<()>)> = self.servers.drain()
+            .map(|(address, server_container)| (address, server_container.task))
+            .collect();
+        let shutdown_deadline = tokio::time::Instant::now() + Duration::from_secs(SHUTDOWN_TIMEOUT_SECS);
+        for (address, server_task) in server_tasks {
+            let abort_handle = server_task.abort_handle();
+            if tokio::time::timeout_at(shutdown_deadline, server_task).await.is_err() {
+                tracing::warn!("Server actor '{}' didn't stop before timeout. Aborting it", address);
+                abort_handle.abort();
+            }
+        }
+
+        self.webhooks.shutdown().await;
+        self.webserver.shutdown().await;
     }
 
     fn register_log_regex_matchers(&mut self) {
-        self.log_regex_matchers.insert(CsctrlLogType::PlayerSay, regex::Regex::new(r#"[0-9\/\ \-\.\:]*\"(?<username>.*)<[0-9]*><\[(?<steam_id>[a-zA-Z]\:[0-9]\:[0-9]*)]><(?<team_side>CT|TERRORIST)>\" (?:say_team|say) \"(?<chat>.*)\""#).unwrap());
-        self.log_regex_matchers.insert(CsctrlLogType::PlayerSwitchTeam, regex::Regex::new(r#"[0-9\/\ \-\.\:]*\"(?<username>.*)<[0-9]*><\[(?<steam_id>[a-zA-Z]\:[0-9]\:[0-9]*)]>\" switched from team <(?<team_from>TERRORIST|CT|Unassigned)> to <(?<team_to>TERRORIST|CT|Unassigned)>"#).unwrap());
+        self.log_regex_matchers.push((CsctrlLogType::PlayerSay, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<username>[^\"]*)<[0-9]*><\[(?<steam_id>[a-zA-Z]\:[0-9]\:[0-9]*)]><(?<team_side>CT|TERRORIST)>\" (?:say_team|say) \"(?<chat>.*)\""#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::PlayerSwitchTeam, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<username>[^\"]*)<(?<user_id>[0-9]*)><\[(?<steam_id>[a-zA-Z]\:[0-9]\:[0-9]*)]>\" switched from team <(?<team_from>TERRORIST|CT|Unassigned)> to <(?<team_to>TERRORIST|CT|Unassigned)>"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::RoundEnd, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*Team \"(?<team_side>CT|TERRORIST)\" triggered \"(?<reason>SFUI_Notice_[a-zA-Z_]*)\" \(CT \"(?<ct_score>[0-9]*)\"\) \(T \"(?<t_score>[0-9]*)\"\)"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::TeamScored, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*Team \"(?<team_side>CT|TERRORIST)\" scored \"(?<score>[0-9]*)\" with \"(?<player_amount>[0-9]*)\" players"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::MatchStatusScore, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*MatchStatus: Score: (?<ct_score>[0-9]*):(?<t_score>[0-9]*) on map \"(?<map>.*)\" RoundsPlayed: (?<rounds_played>-?[0-9]*)"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::FreezePeriodStart, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*Starting Freeze period"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::RoundStart, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*World triggered \"Round_Start\""#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::PlayerKilled, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<attacker_name>[^\"]*?)<[0-9]*><\[?(?<attacker_steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<attacker_team>CT|TERRORIST)>\" \[[0-9\- ]*\] killed \"(?<victim_name>[^\"]*?)<[0-9]*><\[?(?<victim_steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<victim_team>CT|TERRORIST)>\" \[[0-9\- ]*\] with \"(?<weapon>[a-zA-Z0-9_]*)\"(?: \((?<flags>[a-z_ ]*)\))?"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::PlayerSuicide, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<username>[^\"]*?)<[0-9]*><\[?(?<steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<team_side>CT|TERRORIST)>\" \[[0-9\- ]*\] (?:committed suicide with \"(?<weapon>[a-zA-Z0-9_]*)\"|was killed by the bomb)"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::PlayerAssisted, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<attacker_name>[^\"]*?)<[0-9]*><\[?(?<attacker_steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<attacker_team>CT|TERRORIST)>\" (?<assist_type>assisted|flash-assisted) killing \"(?<victim_name>[^\"]*?)<[0-9]*><\[?(?<victim_steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<victim_team>CT|TERRORIST)>\""#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::PlayerAttacked, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<attacker_name>[^\"]*?)<[0-9]*><\[?(?<attacker_steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<attacker_team>CT|TERRORIST)>\" \[[0-9\- ]*\] attacked \"(?<victim_name>[^\"]*?)<[0-9]*><\[?(?<victim_steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<victim_team>CT|TERRORIST)>\" \[[0-9\- ]*\] with \"(?<weapon>[a-zA-Z0-9_]*)\" \(damage \"(?<damage>[0-9]*)\"\)(?: \(damage_armor \"[0-9]*\"\) \(health \"(?<health>[0-9]*)\"\))?"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::PlayerConnected, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<username>[^\"]*?)<(?<user_id>[0-9]*)><\[?(?<steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<team_side>CT|TERRORIST|Unassigned|Spectator)?>\" connected, address \"(?<address>.*)\""#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::PlayerValidated, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<username>[^\"]*?)<(?<user_id>[0-9]*)><\[?(?<steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<team_side>CT|TERRORIST|Unassigned|Spectator)?>\" STEAM USERID validated"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::PlayerEnteredGame, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<username>[^\"]*?)<(?<user_id>[0-9]*)><\[?(?<steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<team_side>CT|TERRORIST|Unassigned|Spectator)?>\" entered the game"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::PlayerDisconnected, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*\"(?<username>[^\"]*?)<(?<user_id>[0-9]*)><\[?(?<steam_id>[a-zA-Z]\:[0-9]\:[0-9]*|BOT)\]?><(?<team_side>CT|TERRORIST|Unassigned|Spectator)?>\" disconnected \(reason \"(?<reason>.*)\"\)"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::GameOver, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*Game Over: (?<game_mode>[a-zA-Z0-9_]*) (?<map_group>\S*) (?<map>\S+) score (?<ct_score>[0-9]*):(?<t_score>[0-9]*) after (?<duration_mins>[0-9]*) min"#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::MapStarted, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*Started map \"(?<map>[^\"]*)\""#).unwrap()));
+        self.log_regex_matchers.push((CsctrlLogType::ServerCvar, regex::Regex::new(r#"^[0-9\/\ \-\.\:]*server_cvar: \"(?<cvar>[a-zA-Z0-9_]*)\" \"(?<value>.*)\""#).unwrap()));
     }
 
     fn reset_registered_servers(&mut self) {
         self.servers.clear();
 
+        let cs_log_url = self.csctrl_config.get_cs_log_url();
         for server in &self.csctrl_config.servers {
             if self.servers.contains_key(server.address.as_str()) {
                 tracing::error!("A server with address '{}' is already registered", server.address);
@@ -122,50 +219,24 @@ impl Csctrl {
             }
 
             let (local_sender, local_receiver) = tokio::sync::mpsc::unbounded_channel();
-            let cloned_server_config = server.clone();
-            let cloned_sender = self.server_threads_sender.get().unwrap().clone();
-            let local_thread = std::thread::Builder::new().name(format!("[{}]", server.address)).spawn(move || {
-                let mut server = CsctrlServer::csctrl_server(cloned_server_config, cloned_sender, local_receiver);
-                server.main();
-            }).unwrap();
+            let csctrl_server = CsctrlServer::csctrl_server(server.clone(), cs_log_url.clone(), get_message_sender().clone());
+            let server_span = tracing::info_span!("server", address = %server.address);
+            let local_task = tokio::spawn(csctrl_server.run(local_receiver).instrument(server_span));
 
             let server_container = CsctrlServerContainer {
-                thread: local_thread,
+                task: local_task,
                 sender: local_sender,
             };
             self.servers.insert(server.address.to_string(), server_container);
 
-            let mut match_setup = MatchSetup {
-                team_a_name: "".to_string(),
-                team_b_name: "".to_string(),
-                knife_round: false,
-                cfg_filename: "".to_string(),
-                player_amount: 0,
-            };
+            let mut match_setup = MatchSetup::default();
 
             match MatchSetup::load_match_setup(&server.match_setup) {
                 Ok(valid_json_setup) => { match_setup = valid_json_setup.clone(); }
                 Err(error) => { tracing::error!(error); }
             }
 
-            get_data().write().unwrap().servers.insert(server.address.to_string(), CsctrlDataServer {
-                config: server.clone(),
-                is_online: false,
-                team_ct: CsctrlDataTeam {
-                    name: "".to_string(),
-                    score: 0,
-                    players: vec![],
-                },
-                team_t: CsctrlDataTeam {
-                    name: "".to_string(),
-                    score: 0,
-                    players: vec![],
-                },
-                status: CsctrlMatchStatus::NoHook,
-                player_ready_amount: 0,
-                logs: vec![],
-                match_setup,
-            });
+            get_data().write().unwrap().servers.insert(server.address.to_string(), CsctrlDataServer::new(server.clone(), match_setup));
 
             self.is_data_dirty = true;
         }
@@ -194,36 +265,35 @@ impl Csctrl {
         registered_commands.insert(command_server_match_start.name(), command_server_match_start);
     }
     
-    fn process_command_messenger(&mut self) {
-        let is_command_messenger_empty = get_command_messenger().read().unwrap().is_empty();
-        if is_command_messenger_empty { return; }
-
-        let command = get_command_messenger().write().unwrap().pop_front().unwrap();
-        self.handle_command(command);
-    }
-
-    fn process_weblog_messenger(&mut self) {
-        let is_weblog_empty = get_weblogs_messenger().read().unwrap().is_empty();
-        if is_weblog_empty { return; }
-
-        let weblog = get_weblogs_messenger().write().unwrap().pop_front().unwrap();
-        let split_weblog: Vec<&str> = weblog.split(FORMAT_SEPARATOR).collect();
-        let address = split_weblog[0].replace("\"", "");
-        let logs = split_weblog[1];
-        let weblog_lines: Vec<&str> = logs.split("\n").collect();
-
-        for line in weblog_lines {
-            if line.contains("CsctrlTerminatingRconCommand") { return; }
-            let mut csctrl_data_servers = &mut get_data().write().unwrap().servers;
-            let mut data_server = csctrl_data_servers.get_mut(&address);
-            if data_server.is_none() { return; }
-            self.handle_weblog(data_server.unwrap(), line);
+    fn handle_message(&mut self, message: CsctrlMessage) {
+        match message {
+            CsctrlMessage::Command { target_address, command, responder } => {
+                self.handle_command(&target_address, &command, responder);
+            }
+            CsctrlMessage::Weblog { server_address, body } => {
+                self.handle_weblog_body(&server_address, &body);
+            }
+            CsctrlMessage::Server { server_address, reply } => {
+                self.handle_server_reply(&server_address, reply);
+            }
+        }
+    }
+
+    fn handle_weblog_body(&mut self, server_address: &str, body: &str) {
+        for line in body.split("\n") {
+            let csctrl_data_servers = &mut get_data().write().unwrap().servers;
+            let data_server = match csctrl_data_servers.get_mut(server_address) {
+                Some(data_server) => { data_server }
+                None => { return; }
+            };
+            self.handle_weblog(data_server, line);
         }
     }
 
     fn handle_weblog(&mut self, server_data: &mut CsctrlDataServer, log_line: &str) {
         self.is_data_dirty = true;
         server_data.is_online = true;
+        server_data.health.last_seen_at = Some(get_unix_timestamp_secs());
 
         let log_line = self.process_and_get_server_log(server_data, log_line);
         server_data.logs.push(log_line);
@@ -231,22 +301,48 @@ impl Csctrl {
 
     fn handle_dirty_data(&mut self) {
         self.is_data_dirty = false;
+        self.broadcast_state_changes();
         if *self.terminal.is_terminal_active() {
             self.terminal.update_cached_server_data(get_data().read().unwrap().deref().clone());
         }
     }
 
-    fn handle_command(&mut self, command_string: String) {
-        let split_target_address: Vec<&str> = command_string.split("<csctrlseptarget>").collect();
-        let target_address = split_target_address[1];
-        let trimmed_string = split_target_address[2].trim();
+    /// Status and score can change from many places so they are compared against what was last broadcast instead
+    fn broadcast_state_changes(&mut self) {
+        let data = get_data().read().unwrap();
+        for (address, server_data) in &data.servers {
+            let current_state = (server_data.status.clone(), server_data.team_ct.score, server_data.team_t.score);
+            let last_state = self.last_broadcast_states.get(address).cloned();
+            if last_state.as_ref() == Some(&current_state) { continue; }
+
+            if last_state.as_ref().is_none_or(|last_state| last_state.0 != current_state.0) {
+                broadcast_event(address, CsctrlEvent::StatusChange {
+                    status: current_state.0.clone(),
+                    previous_status: last_state.as_ref().map(|last_state| last_state.0.clone()),
+                });
+            }
+            if last_state.as_ref().is_none_or(|last_state| last_state.1 != current_state.1 || last_state.2 != current_state.2) {
+                broadcast_event(address, CsctrlEvent::ScoreChange {
+                    team_ct_name: server_data.team_ct.name.clone(),
+                    team_ct_score: current_state.1,
+                    team_t_name: server_data.team_t.name.clone(),
+                    team_t_score: current_state.2,
+                });
+            }
+            self.last_broadcast_states.insert(address.clone(), current_state);
+        }
+    }
+
+    /// Commands that answer later, like rcon, take the responder out of `command_responder` while they run
+    fn handle_command(&mut self, target_address: &str, command_string: &str, responder: Option<tokio::sync::oneshot::Sender<CommandResult>>) {
+        let trimmed_string = command_string.trim();
         let split_string: Vec<&str> = trimmed_string.split(" ").collect();
 
+        self.command_responder = responder;
         let registered_commands = get_registered_commands().read().unwrap();
-        match registered_commands.get(split_string[0]) {
+        let command_result = match registered_commands.get(split_string[0]) {
             None => {
-                tracing::error!("No command '{}' exists", split_string[0]);
-                return;
+                Err(format!("No command '{}' exists", split_string[0]))
             }
             Some(found_command) => {
                 let mut arguments = "".to_string();
@@ -262,57 +358,349 @@ impl Csctrl {
                 tracing::trace!("Executing command '{}' on target '{}' with arguments '{}'", split_string[0], target_address, arguments.trim_end());
                 found_command.exec(self, target_address.to_string(), arguments.trim_end().to_string())
             }
+        };
+
+        if let Err(error) = &command_result {
+            tracing::error!(error);
+        }
+
+        if let Some(responder) = self.command_responder.take() {
+            let _ = responder.send(command_result);
+        }
+    }
+
+    /// Keeps the command and the current command's responder until the server thread answers the rcon request with this id
+    pub fn register_rcon_request(&mut self, target_address: &str, command: &str) -> u64 {
+        self.next_rcon_request_id += 1;
+        self.pending_rcon_requests.insert(self.next_rcon_request_id, CsctrlRconRequest {
+            target_address: target_address.to_string(),
+            command: command.to_string(),
+            responder: self.command_responder.take(),
+            registered_at: std::time::Instant::now(),
+        });
+        return self.next_rcon_request_id;
+    }
+
+    /// Fails requests that never got an answer so they don't pile up and their callers hear back
+    fn process_rcon_request_timeouts(&mut self) {
+        let timed_out_request_ids: Vec<u64> = self.pending_rcon_requests.iter()
+            .filter(|(_, rcon_request)| rcon_request.registered_at.elapsed().as_secs() >= RCON_REQUEST_TIMEOUT_SECS)
+            .map(|(request_id, _)| *request_id)
+            .collect();
+
+        for request_id in timed_out_request_ids {
+            self.handle_rcon_result(request_id, Err("Server didn't answer before timeout".to_string()));
         }
     }
 
-    fn process_server_receiver_channel(&mut self) {
-        let received_message = match self.server_threads_receiver.get_mut().unwrap().try_recv() {
-            Ok(message) => { message }
-            Err(_) => { return; }
+    /// Hands the responder back to the current command so its error result reaches the caller
+    pub fn cancel_rcon_request(&mut self, request_id: u64) {
+        if let Some(rcon_request) = self.pending_rcon_requests.remove(&request_id) {
+            self.command_responder = rcon_request.responder;
+        }
+    }
+
+    fn handle_rcon_result(&mut self, request_id: u64, result: CommandResult) {
+        let rcon_request = match self.pending_rcon_requests.remove(&request_id) {
+            Some(rcon_request) => { rcon_request }
+            None => { return; }
+        };
+
+        let (is_success, response) = match &result {
+            Ok(response) => { (true, response.clone()) }
+            Err(error) => { (false, error.clone()) }
+        };
+        let log_title = if is_success {
+            format!("[{}] rcon {}", rcon_request.target_address, rcon_request.command)
+        } else {
+            format!("[{}] rcon {} failed: {}", rcon_request.target_address, rcon_request.command, response)
         };
+        self.terminal.add_log(&log_title);
+        if is_success {
+            for response_line in response.lines() {
+                self.terminal.add_log(&format!("  {}", response_line));
+            }
+        }
+
+        if let Some(responder) = rcon_request.responder {
+            let _ = responder.send(result);
+        }
+    }
+
+    fn handle_server_reply(&mut self, server_address: &str, reply: CsctrlServerReply) {
+        match reply {
+            CsctrlServerReply::MatchStatus(match_status) => { self.handle_match_status(server_address, match_status); }
+            CsctrlServerReply::RconResult { request_id, result } => { self.handle_rcon_result(request_id, result); }
+            CsctrlServerReply::HealthCheckSucceeded { latency_ms, status } => { self.handle_server_health(server_address, Some((latency_ms, status))); }
+            CsctrlServerReply::HealthCheckFailed => { self.handle_server_health(server_address, None); }
+        }
+    }
 
-        let source_address_and_content: Vec<&str> = received_message.split(FORMAT_SEPARATOR).collect();
-        if source_address_and_content[1].contains("CsctrlMatchStatus") {
-            let match_status_and_value: Vec<&str> = source_address_and_content[1].split(":").collect();
-            let mut data_write_lock = get_data().write().unwrap();
-            let source_server = data_write_lock.servers.get_mut(source_address_and_content[0]);
-            if source_server.is_none() {
-                tracing::error!("Can't find data for server '{}'", source_address_and_content[0]);
+    fn handle_match_status(&mut self, server_address: &str, match_status: CsctrlMatchStatus) {
+        let mut data_write_lock = get_data().write().unwrap();
+        let source_server = match data_write_lock.servers.get_mut(server_address) {
+            Some(source_server) => { source_server }
+            None => {
+                tracing::error!("Can't find data for server '{}'", server_address);
                 return;
             }
-            let match_status = CsctrlMatchStatus::string_to_enum(match_status_and_value[1]);
-            if match_status == CsctrlMatchStatus::Invalid {
+        };
+
+        if match_status == CsctrlMatchStatus::PreMatchWarmup {
+            let (team_ct, team_t) = source_server.get_starting_teams();
+            let (team_ct_name, team_t_name) = source_server.get_starting_team_names();
+            source_server.team_ct.setup_team = team_ct;
+            source_server.team_t.setup_team = team_t;
+            source_server.team_ct.name = team_ct_name;
+            source_server.team_t.name = team_t_name;
+            source_server.team_ct.score = 0;
+            source_server.team_t.score = 0;
+            source_server.player_ready_amount = 0;
+            source_server.knife_winner_side = "".to_string();
+            source_server.rounds_played = 0;
+            source_server.last_round_winner_side = "".to_string();
+            source_server.last_round_reason = "".to_string();
+            source_server.is_side_swap_pending = false;
+            source_server.player_stats.clear();
+            source_server.pauses.clear();
+            source_server.match_started_at = 0;
+            for player in source_server.team_ct.players.iter_mut().chain(source_server.team_t.players.iter_mut()) {
+                player.is_ready = false;
+            }
+        }
+
+        // The status turns live when the restarts are requested, so whatever the logs counted
+        // before the server thread confirms the match is live isn't part of the match
+        if match_status == CsctrlMatchStatus::Live && source_server.match_started_at == 0 {
+            source_server.match_started_at = get_unix_timestamp_secs();
+            source_server.team_ct.score = 0;
+            source_server.team_t.score = 0;
+            source_server.rounds_played = 0;
+            source_server.last_round_winner_side = "".to_string();
+            source_server.last_round_reason = "".to_string();
+            source_server.is_side_swap_pending = false;
+            source_server.player_stats.clear();
+        }
+
+        if match_status == CsctrlMatchStatus::NoHook {
+            source_server.player_ready_amount = 0;
+            source_server.knife_winner_side = "".to_string();
+            source_server.is_side_swap_pending = false;
+            for player in source_server.team_ct.players.iter_mut().chain(source_server.team_t.players.iter_mut()) {
+                player.is_ready = false;
+            }
+        }
+
+        // A failed pause request leaves a pause behind that never took effect on the server
+        if match_status == CsctrlMatchStatus::Live {
+            if let Some(active_pause) = source_server.get_active_pause() {
+                active_pause.ended_at = Some(get_unix_timestamp_secs());
+            }
+        }
+
+        source_server.status = match_status;
+        self.is_data_dirty = true;
+    }
+
+    /// A single failed probe can be a hiccup, the server is only considered offline after a few in a row.
+    /// A successful probe carries its latency and the 'status' output
+    fn handle_server_health(&mut self, address: &str, health_check: Option<(u64, String)>) {
+        let mut data_write_lock = get_data().write().unwrap();
+        let server_data = match data_write_lock.servers.get_mut(address) {
+            Some(server_data) => { server_data }
+            None => {
+                tracing::error!("Can't find data for server '{}'", address);
                 return;
             }
+        };
+
+        let now = get_unix_timestamp_secs();
+        server_data.health.last_check_at = Some(now);
+        if let Some((latency_ms, status)) = health_check {
+            server_data.health.latency_ms = Some(latency_ms);
+            server_data.health.last_seen_at = Some(now);
+            server_data.health.consecutive_failures = 0;
+            if !server_data.is_online { tracing::info!("Server '{}' is online", address); }
+            server_data.is_online = true;
+            self.update_server_info(server_data, &status);
+        }
+        else {
+            server_data.health.latency_ms = None;
+            server_data.health.consecutive_failures = server_data.health.consecutive_failures.saturating_add(1);
+            if server_data.is_online && server_data.health.consecutive_failures >= HEALTH_CHECK_FAILURES_BEFORE_OFFLINE {
+                tracing::warn!("Server '{}' is offline after {} failed health checks", address, server_data.health.consecutive_failures);
+                server_data.is_online = false;
+            }
+        }
+        self.is_data_dirty = true;
+    }
+
+    /// Logged connections fill in the steam ids 'status' doesn't list. The map is only taken while no
+    /// log line has reported one so it can't race the map changes driven by the logs
+    fn update_server_info(&self, server_data: &mut CsctrlDataServer, status: &str) {
+        let mut server_info = parse_server_status(status);
+        for status_player in server_info.players.iter_mut() {
+            if status_player.steam_id.is_some() { continue; }
+            status_player.steam_id = server_data.connections.values()
+                .find(|connection| connection.user_id == status_player.user_id && connection.name == status_player.name)
+                .map(|connection| connection.steam_id);
+        }
+
+        if server_data.map.is_empty() && !server_info.map.is_empty() {
+            server_data.map = server_info.map.clone();
+        }
+        server_info.updated_at = Some(get_unix_timestamp_secs());
+        server_data.server_info = server_info;
+    }
+
+    fn process_pause_timeouts(&mut self) {
+        let mut data_write_lock = get_data().write().unwrap();
+        for (address, server_data) in data_write_lock.servers.iter_mut() {
+            if server_data.status != CsctrlMatchStatus::Paused { continue; }
+
+            let tactical_timeout_duration_secs = server_data.match_setup.tactical_timeout_duration_secs;
+            let active_pause = match server_data.get_active_pause() {
+                Some(pause) => { pause }
+                None => { continue; }
+            };
+            if active_pause.pause_type != CsctrlPauseType::Tactical { continue; }
+            let started_at = match active_pause.started_at {
+                Some(started_at) => { started_at }
+                None => { continue; }
+            };
+            if get_unix_timestamp_secs() - started_at < tactical_timeout_duration_secs { continue; }
+
+            if !self.send_message_to_server_thread(address, CsctrlServerRequest::MatchUnpause { announcement: "Tactical timeout is over".to_string() }) { continue; }
 
-            source_server.unwrap().status = match_status;
+            active_pause.ended_at = Some(get_unix_timestamp_secs());
+            server_data.status = CsctrlMatchStatus::Live;
+            self.is_data_dirty = true;
+        }
+    }
+
+    fn process_ready_checks(&mut self) {
+        let mut data_write_lock = get_data().write().unwrap();
+        for (address, server_data) in data_write_lock.servers.iter_mut() {
+            if server_data.status != CsctrlMatchStatus::PreMatchWarmup { continue; }
+            if server_data.match_setup.player_amount < 1 { continue; }
+            if server_data.player_ready_amount < server_data.match_setup.player_amount { continue; }
+
+            let (server_request, next_status) = if server_data.uses_knife_round() {
+                (CsctrlServerRequest::MatchKnife, CsctrlMatchStatus::KnifeRound)
+            } else {
+                (CsctrlServerRequest::MatchLive, CsctrlMatchStatus::Live)
+            };
+
+            if !self.send_message_to_server_thread(address, server_request) { continue; }
+
+            // The server thread reports PreMatchWarmup back if it fails to start the match
+            tracing::info!("All {} players are ready on server '{}'", server_data.player_ready_amount, address);
+            server_data.status = next_status;
             self.is_data_dirty = true;
         }
     }
 
     fn process_and_get_server_log(&mut self, server_data: &mut CsctrlDataServer, unprocessed_server_log: &str) -> String {
-        let mut log_type: &CsctrlLogType = &Invalid;
-        let mut regex: &Regex = &Regex::new("").unwrap();
-        for (log_type_iter, regex_iter) in &self.log_regex_matchers {
-            if !regex_iter.is_match(unprocessed_server_log) {
-                continue;
+        let mut log_type: CsctrlLogType = Invalid;
+        let mut regex_captures = None;
+        for (log_type_iter, regex_iter) in self.log_regex_matchers.iter() {
+            if let Some(captures) = regex_iter.captures(unprocessed_server_log) {
+                log_type = log_type_iter.clone();
+                regex_captures = Some(captures);
+                break;
             }
-            log_type = log_type_iter;
-            regex = regex_iter;
-            break;
         }
-        if log_type == &Invalid { return unprocessed_server_log.to_string(); }
+        let regex_captures = match regex_captures {
+            Some(regex_captures) => { regex_captures }
+            None => { return unprocessed_server_log.to_string(); }
+        };
 
-        let regex_captures = regex.captures(unprocessed_server_log).unwrap();
         match log_type {
             CsctrlLogType::PlayerSay => { csctrl::log_events::player_say(self, server_data, &regex_captures) }
             CsctrlLogType::PlayerSwitchTeam => { csctrl::log_events::player_switch_team(self, server_data, &regex_captures) }
+            CsctrlLogType::RoundEnd => { csctrl::log_events::round_end(self, server_data, &regex_captures) }
+            CsctrlLogType::TeamScored => { csctrl::log_events::team_scored(self, server_data, &regex_captures) }
+            CsctrlLogType::MatchStatusScore => { csctrl::log_events::match_status_score(self, server_data, &regex_captures) }
+            CsctrlLogType::FreezePeriodStart => { csctrl::log_events::freeze_period_start(self, server_data) }
+            CsctrlLogType::RoundStart => { csctrl::log_events::round_start(server_data) }
+            CsctrlLogType::PlayerKilled => { csctrl::log_events::player_killed(self, server_data, &regex_captures) }
+            CsctrlLogType::PlayerSuicide => { csctrl::log_events::player_suicide(self, server_data, &regex_captures) }
+            CsctrlLogType::PlayerAssisted => { csctrl::log_events::player_assisted(self, server_data, &regex_captures) }
+            CsctrlLogType::PlayerAttacked => { csctrl::log_events::player_attacked(self, server_data, &regex_captures) }
+            CsctrlLogType::PlayerConnected => { csctrl::log_events::player_connected(self, server_data, &regex_captures) }
+            CsctrlLogType::PlayerValidated => { csctrl::log_events::player_connection_state(self, server_data, &regex_captures, CsctrlConnectionState::Validated) }
+            CsctrlLogType::PlayerEnteredGame => { csctrl::log_events::player_connection_state(self, server_data, &regex_captures, CsctrlConnectionState::InGame) }
+            CsctrlLogType::PlayerDisconnected => { csctrl::log_events::player_disconnected(self, server_data, &regex_captures) }
+            CsctrlLogType::GameOver => { csctrl::log_events::game_over(self, server_data, &regex_captures) }
+            CsctrlLogType::MapStarted => { csctrl::log_events::map_started(self, server_data, &regex_captures) }
+            CsctrlLogType::ServerCvar => { csctrl::log_events::server_cvar(self, server_data, &regex_captures) }
             _ => {}
         }
 
         return unprocessed_server_log.to_string();
     }
 
+    /// Starts the veto when one is configured, then changes to the current series map before warming up
+    pub fn start_server_match(&mut self, server_data: &mut CsctrlDataServer) -> CommandResult {
+        let address = server_data.config.address.clone();
+        if !server_data.series.is_active {
+            if let Some(veto_setup) = &server_data.match_setup.veto {
+                server_data.veto = CsctrlDataVeto {
+                    remaining_maps: veto_setup.map_pool.clone(),
+                    actions: vec![],
+                };
+                server_data.status = CsctrlMatchStatus::Veto;
+                self.is_data_dirty = true;
+                csctrl::log_events::announce_veto_step(self, server_data);
+                return Ok(format!("Started map veto on server '{}'", address));
+            }
+
+            if !server_data.match_setup.maps.is_empty() {
+                server_data.series = CsctrlDataSeries {
+                    is_active: true,
+                    maps: server_data.match_setup.maps.clone(),
+                    ..Default::default()
+                };
+            }
+        }
+
+        // Series maps are started once the server logs that the map finished loading
+        if let Some(series_map) = server_data.get_current_series_map() {
+            if series_map.name != server_data.map {
+                let series_map_name = series_map.name.clone();
+                if !self.send_message_to_server_thread(&address, CsctrlServerRequest::ChangeLevel { map: series_map_name.clone() }) {
+                    return Err(format!("Can't change server '{}' to map '{}'", address, series_map_name));
+                }
+                server_data.status = CsctrlMatchStatus::ChangingMap;
+                self.is_data_dirty = true;
+                return Ok(format!("Changing server '{}' to map '{}'", address, series_map_name));
+            }
+        }
+
+        if !self.send_message_to_server_thread(&address, CsctrlServerRequest::MatchStart) {
+            return Err(format!("Can't start the match on server '{}'", address));
+        }
+        return Ok(format!("Loading match on server '{}'", address));
+    }
+
+    pub fn send_message_to_server_thread(&self, address: &str, request: CsctrlServerRequest) -> bool {
+        let found_server = match self.servers.get(address) {
+            Some(server) => { server }
+            None => {
+                tracing::error!("No server thread with address '{}' exists", address);
+                return false;
+            }
+        };
+
+        return match found_server.sender.send(request) {
+            Ok(_) => { true }
+            Err(error) => {
+                tracing::error!("Can't send message to thread belonging to server '{}'. Error: {}", address, error);
+                false
+            }
+        };
+    }
+
     pub fn set_data_dirty(&mut self) {
         self.is_data_dirty = true;
     }
@@ -320,10 +708,147 @@ impl Csctrl {
     pub fn write_config(&self) {
         system::utilities::write_config(&self.csctrl_config);
     }
+}
 
-    pub fn has_requested_exit(&self) -> &bool {
-        return &self.requested_exit;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use crate::csctrl::steam_id::SteamId;
+    use crate::csctrl::types::{CsctrlConfig, CsctrlDataPlayer, CsctrlDataPlayerStats, CsctrlServerSetup};
+
+    const LOG_PREFIX: &str = "10/18/2026 - 20:15:42.123 - ";
+
+    fn create_test_csctrl() -> Csctrl {
+        let mut csctrl = Csctrl::create_csctrl(CsctrlConfig {
+            api_token: "".to_string(),
+            chat_signature: "csctrl".to_string(),
+            cs_log_address: "".to_string(),
+            cs_listen_path: "/cslog".to_string(),
+            rest_api_address: "127.0.0.1:27016".to_string(),
+            secret: "secret".to_string(),
+            servers: vec![],
+            tracing_env_filter: "".to_string(),
+            webhooks: vec![],
+        }, true);
+        csctrl.register_log_regex_matchers();
+        return csctrl;
     }
-}
 
+    fn create_test_player(name: &str, steam_id: &str) -> CsctrlDataPlayer {
+        return CsctrlDataPlayer { name: name.to_string(), steam_id: SteamId::from_str(steam_id).unwrap(), is_ready: true };
+    }
 
+    /// A live match with Eve and Frank ready on CT and Victor ready on T
+    fn create_live_server_data() -> CsctrlDataServer {
+        let mut server_data = CsctrlDataServer::new(CsctrlServerSetup {
+            name: "Test".to_string(),
+            address: "127.0.0.1:27015".to_string(),
+            rcon_password: "".to_string(),
+            match_setup: "".to_string(),
+            webhooks: vec![],
+        }, MatchSetup::default());
+        server_data.status = CsctrlMatchStatus::Live;
+        server_data.team_ct.players.push(create_test_player("Eve", "[U:1:1]"));
+        server_data.team_ct.players.push(create_test_player("Frank", "[U:1:5]"));
+        server_data.team_t.players.push(create_test_player("Victor", "[U:1:6]"));
+        server_data.player_ready_amount = 3;
+        return server_data;
+    }
+
+    fn process_log_lines(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, log_lines: &[&str]) {
+        for log_line in log_lines {
+            csctrl.process_and_get_server_log(server_data, &format!("{}{}", LOG_PREFIX, log_line));
+        }
+    }
+
+    fn get_test_player_stats<'a>(server_data: &'a CsctrlDataServer, steam_id: &str) -> Option<&'a CsctrlDataPlayerStats> {
+        return server_data.player_stats.get(&SteamId::from_str(steam_id).unwrap().to_string());
+    }
+
+    #[test]
+    fn counts_logged_kills_damage_and_assists() {
+        let mut csctrl = create_test_csctrl();
+        let mut server_data = create_live_server_data();
+
+        process_log_lines(&mut csctrl, &mut server_data, &[
+            r#""Frank<3><[U:1:5]><CT>" [-10 20 30] attacked "Victor<4><[U:1:6]><TERRORIST>" [40 50 60] with "ak47" (damage "27") (damage_armor "3") (health "73") (armor "97") (hitgroup "chest")"#,
+            r#""Eve<2><[U:1:1]><CT>" [-10 20 30] killed "Victor<4><[U:1:6]><TERRORIST>" [40 50 60] with "ak47" (headshot)"#,
+            r#""Frank<3><[U:1:5]><CT>" assisted killing "Victor<4><[U:1:6]><TERRORIST>""#,
+        ]);
+
+        let killer_stats = get_test_player_stats(&server_data, "[U:1:1]").unwrap();
+        assert_eq!((killer_stats.kills, killer_stats.headshot_kills), (1, 1));
+        let assister_stats = get_test_player_stats(&server_data, "[U:1:5]").unwrap();
+        assert_eq!((assister_stats.assists, assister_stats.damage), (1, 27));
+        assert_eq!(get_test_player_stats(&server_data, "[U:1:6]").unwrap().deaths, 1);
+    }
+
+    #[test]
+    fn ignores_combat_events_forged_through_chat() {
+        let mut csctrl = create_test_csctrl();
+        let mut server_data = create_live_server_data();
+
+        process_log_lines(&mut csctrl, &mut server_data, &[
+            r#""Eve<2><[U:1:1]><CT>" say "<3><[U:1:5]><CT>" [0 0 0] killed "V<4><[U:1:6]><TERRORIST>" [0 0 0] with "ak47" (headshot)""#,
+            r#""Eve<2><[U:1:1]><CT>" say_team "<3><[U:1:5]><CT>" [0 0 0] attacked "V<4><[U:1:6]><TERRORIST>" [0 0 0] with "ak47" (damage "100") (damage_armor "0") (health "0")""#,
+            r#""Eve<2><[U:1:1]><CT>" say "<3><[U:1:5]><CT>" assisted killing "V<4><[U:1:6]><TERRORIST>"""#,
+            r#""Eve<2><[U:1:1]><CT>" say "<4><[U:1:6]><TERRORIST>" [0 0 0] committed suicide with "world"""#,
+        ]);
+
+        assert!(server_data.player_stats.is_empty());
+    }
+
+    #[test]
+    fn ignores_connection_events_and_chat_forged_through_chat() {
+        let mut csctrl = create_test_csctrl();
+        let mut server_data = create_live_server_data();
+
+        process_log_lines(&mut csctrl, &mut server_data, &[
+            r#""Eve<2><[U:1:1]><CT>" say "<3><[U:1:5]><CT>" disconnected (reason "x")""#,
+            r#""Eve<2><[U:1:1]><CT>" say "<3><[U:1:5]><CT>" switched from team <CT> to <TERRORIST>""#,
+            r#""Eve<2><[U:1:1]><CT>" say "Frank<3><[U:1:5]><CT>" say ".unready""#,
+        ]);
+
+        // The chat command belongs to whoever sent it
+        let is_ready = |name: &str| server_data.team_ct.players.iter().find(|player| player.name == name).unwrap().is_ready;
+        assert!(!is_ready("Eve"));
+        assert!(is_ready("Frank"));
+        assert_eq!(server_data.player_ready_amount, 2);
+        assert!(server_data.connections.is_empty());
+    }
+
+    #[test]
+    fn drops_rounds_logged_before_the_match_is_confirmed_live() {
+        let mut csctrl = create_test_csctrl();
+        let mut server_data = create_live_server_data();
+        server_data.config.address = "127.0.0.1:27101".to_string();
+
+        // The warmup restarts still log rounds and kills while the server thread counts down
+        process_log_lines(&mut csctrl, &mut server_data, &[
+            r#""Eve<2><[U:1:1]><CT>" [-10 20 30] killed "Victor<4><[U:1:6]><TERRORIST>" [40 50 60] with "ak47""#,
+            r#"Team "CT" triggered "SFUI_Notice_CTs_Win" (CT "1") (T "0")"#,
+        ]);
+        assert!(!server_data.player_stats.is_empty());
+        get_data().write().unwrap().servers.insert(server_data.config.address.clone(), server_data);
+
+        csctrl.handle_match_status("127.0.0.1:27101", CsctrlMatchStatus::Live);
+
+        let data = get_data().read().unwrap();
+        let server_data = data.servers.get("127.0.0.1:27101").unwrap();
+        assert!(server_data.match_started_at > 0);
+        assert!(server_data.player_stats.is_empty());
+        assert_eq!((server_data.team_ct.score, server_data.team_t.score, server_data.rounds_played), (0, 0, 0));
+    }
+
+    #[test]
+    fn removes_disconnected_players_from_their_team() {
+        let mut csctrl = create_test_csctrl();
+        let mut server_data = create_live_server_data();
+
+        process_log_lines(&mut csctrl, &mut server_data, &[r#""Frank<3><[U:1:5]><CT>" disconnected (reason "Disconnect")"#]);
+
+        assert!(server_data.team_ct.players.iter().all(|player| player.name != "Frank"));
+        assert_eq!(server_data.player_ready_amount, 2);
+    }
+}
@@ -1,14 +1,18 @@
+use std::cmp;
+use std::str::FromStr;
 use regex::{Captures, Regex};
-use crate::csctrl::csctrl::{Csctrl, get_data};
-use crate::csctrl::types::{CsctrlDataPlayer, CsctrlDataServer};
+use crate::csctrl::csctrl::{broadcast_event, Csctrl, get_data};
+use crate::csctrl::types::{CsctrlEvent, CsctrlConnectionState, CsctrlDataConnection, CsctrlDataPause, CsctrlDataPlayer, CsctrlDataPlayerStats, CsctrlDataServer, CsctrlMatchResult, CsctrlMatchResultPlayer, CsctrlMatchResultTeam, CsctrlMatchStatus, CsctrlPauseType, CsctrlSeriesMapResult, CsctrlSeriesResult, CsctrlServerRequest, CsctrlDataSeries, CsctrlVetoAction, MatchSetupMap, MatchSetupMapSide, MatchSetupTeam, MatchSetupVetoAction};
+use crate::csctrl::steam_id::SteamId;
+use crate::system::utilities::{get_unix_timestamp_secs, write_match_log};
 
-fn find_player_index_and_team_with_steamid3<'a>(server_data: &'a mut CsctrlDataServer, steam_id_3: &str, out_player_team: &mut String) -> i32 {
+fn find_player_index_and_team_with_steam_id(server_data: &mut CsctrlDataServer, steam_id: &SteamId, out_player_team: &mut String) -> i32 {
     let mut index = 0;
     let ct_players = &mut server_data.team_ct.players;
     let t_players = &mut server_data.team_t.players;
 
     for player in ct_players.clone() {
-        if player.steam3 != steam_id_3 {
+        if player.steam_id != *steam_id {
             index = index + 1;
             continue;
         }
@@ -18,7 +22,7 @@ fn find_player_index_and_team_with_steamid3<'a>(server_data: &'a mut CsctrlDataS
 
     index = 0;
     for player in t_players.clone() {
-        if player.steam3 != steam_id_3 {
+        if player.steam_id != *steam_id {
             index = index + 1;
             continue;
         }
@@ -31,77 +35,873 @@ fn find_player_index_and_team_with_steamid3<'a>(server_data: &'a mut CsctrlDataS
 
 pub fn player_say(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
     let chat = regex_captures["chat"].to_string();
-    let steam_id = regex_captures["steam_id"].to_string();
+    let steam_id = match parse_steam_id(&regex_captures["steam_id"]) {
+        Some(steam_id) => { steam_id }
+        None => { return; }
+    };
+
+    broadcast_event(&server_data.config.address, CsctrlEvent::Chat {
+        player_name: regex_captures["username"].to_string(),
+        steam_id,
+        team_side: regex_captures["team_side"].to_string(),
+        message: chat.clone(),
+    });
 
     let ready_command = chat.contains(".ready");
     let unready_command = chat.contains(".unready");
 
     let mut player_team: String = "".to_string();
-    let player_index = find_player_index_and_team_with_steamid3(server_data, &steam_id, &mut player_team);
+    let player_index = find_player_index_and_team_with_steam_id(server_data, &steam_id, &mut player_team);
     if player_index < 0 {
-        tracing::error!("No record of a player on server with steamid3 '{}'", steam_id);
+        tracing::error!("No record of a player on server with steam id '{}'", steam_id);
         return;
     }
-    let mut found_player = if player_team.eq_ignore_ascii_case("TERRORIST") {
-        server_data.team_t.players.get_mut(player_index as usize).unwrap()
-    } else {
-        server_data.team_ct.players.get_mut(player_index as usize).unwrap()
-    };
 
+    if server_data.status == CsctrlMatchStatus::Veto {
+        veto_command(csctrl, server_data, &chat, &steam_id, &player_team);
+        return;
+    }
+
+    if server_data.status == CsctrlMatchStatus::SwitchTeamsWarmup {
+        knife_side_choice(csctrl, server_data, &chat, &player_team);
+        return;
+    }
+
+    if chat.contains(".pause") {
+        request_pause(csctrl, server_data, &player_team, CsctrlPauseType::Tactical);
+        return;
+    }
+    if chat.contains(".tech") {
+        request_pause(csctrl, server_data, &player_team, CsctrlPauseType::Technical);
+        return;
+    }
+    if chat.contains(".unpause") {
+        request_unpause(csctrl, server_data, &player_team);
+        return;
+    }
     if !ready_command && !unready_command {
         return;
     }
 
     let is_player_ready = ready_command && !unready_command;
+    if is_player_ready {
+        if let Err(reason) = validate_roster_side(server_data, &steam_id, &player_team) {
+            csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::Say { message: reason.to_string() });
+            return;
+        }
+    }
+
+    let found_player = if player_team.eq_ignore_ascii_case("TERRORIST") {
+        server_data.team_t.players.get_mut(player_index as usize).unwrap()
+    } else {
+        server_data.team_ct.players.get_mut(player_index as usize).unwrap()
+    };
+
     let current_player_ready_status = found_player.is_ready;
     if is_player_ready == current_player_ready_status { return; }
+    let player_name = found_player.name.clone();
 
     if is_player_ready {
         found_player.is_ready = true;
-        server_data.player_ready_amount = server_data.player_ready_amount + 1;
+        server_data.player_ready_amount += 1;
     } else {
         found_player.is_ready = false;
-        server_data.player_ready_amount = server_data.player_ready_amount - 1;
+        server_data.player_ready_amount -= 1;
     }
 
+    broadcast_event(&server_data.config.address, CsctrlEvent::ReadyChange {
+        player_name,
+        steam_id,
+        is_ready: is_player_ready,
+        player_ready_amount: server_data.player_ready_amount,
+    });
     csctrl.set_data_dirty();
 }
 
 pub fn player_switch_team(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
-    let steam_id = regex_captures["steam_id"].to_string();
+    let steam_id = match parse_steam_id(&regex_captures["steam_id"]) {
+        Some(steam_id) => { steam_id }
+        None => { return; }
+    };
     let player_username = regex_captures["username"].to_string();
     let player_data = CsctrlDataPlayer {
         name: player_username,
-        steam3: steam_id.clone(),
+        steam_id,
         is_ready: false,
     };
 
     let team_to = regex_captures["team_to"].to_string();
     let team_from = regex_captures["team_from"].to_string();
-    if team_from.eq_ignore_ascii_case("Unassigned") {
-        if team_to.eq_ignore_ascii_case("TERRORIST") {
-            server_data.team_t.players.push(player_data);
-        } else if team_to.eq_ignore_ascii_case("CT") {
-            server_data.team_ct.players.push(player_data);
+    if !team_from.eq_ignore_ascii_case("Unassigned") {
+        remove_player_from_teams(server_data, &steam_id);
+    }
+
+    if team_to.eq_ignore_ascii_case("TERRORIST") {
+        server_data.team_t.players.push(player_data);
+    } else if team_to.eq_ignore_ascii_case("CT") {
+        server_data.team_ct.players.push(player_data);
+    }
+
+    let is_on_playing_side = team_to.eq_ignore_ascii_case("TERRORIST") || team_to.eq_ignore_ascii_case("CT");
+    if let (true, Err(reason)) = (is_on_playing_side, validate_roster_side(server_data, &steam_id, &team_to)) {
+        // CS2 can't move a player to another side over rcon, so roster players picking the wrong side in warmup are kicked
+        // and reconnect to the right one. Later phases swap sides in the logs before csctrl does, so they only get a warning
+        match get_roster_side(server_data, &steam_id) {
+            Some(expected_side) if server_data.status == CsctrlMatchStatus::PreMatchWarmup => {
+                let user_id: u16 = regex_captures["user_id"].parse().unwrap_or(0);
+                tracing::info!("Kicking '{}' ({}) from server '{}' since they joined the wrong side", &regex_captures["username"], steam_id, server_data.config.address);
+                csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::Kick { user_id, reason: format!("You play on the {} side. Reconnect and join it", expected_side) });
+            }
+            _ => {
+                csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::Say { message: format!("{}: {}", &regex_captures["username"], reason) });
+            }
+        }
+    }
+
+    csctrl.set_data_dirty();
+}
+
+/// Returns the side a roster player has to play on. Coaches, players of teams without a roster
+/// and servers that haven't loaded a match setup yet have none
+fn get_roster_side(server_data: &CsctrlDataServer, steam_id: &SteamId) -> Option<&'static str> {
+    if server_data.status == CsctrlMatchStatus::NoHook { return None; }
+
+    let roster_team = match server_data.match_setup.get_roster_team(steam_id) {
+        Some((roster_team, false)) => { roster_team }
+        _ => { return None; }
+    };
+
+    if server_data.team_ct.setup_team == roster_team { return Some("CT"); }
+    return Some("TERRORIST");
+}
+
+/// Players on the wrong side of their roster can't ready up
+fn validate_roster_side(server_data: &CsctrlDataServer, steam_id: &SteamId, player_team: &str) -> Result<(), String> {
+    let roster_team = match server_data.match_setup.get_roster_team(steam_id) {
+        Some((roster_team, false)) => { roster_team }
+        Some((_, true)) => { return Err("Coaches don't take part in the ready check".to_string()); }
+        None => {
+            if server_data.match_setup.has_every_roster() { return Err("You are not on any team roster".to_string()); }
+            return Ok(());
+        }
+    };
+
+    let expected_side = match get_roster_side(server_data, steam_id) {
+        Some(expected_side) => { expected_side }
+        None => { return Ok(()); }
+    };
+
+    if !player_team.eq_ignore_ascii_case(expected_side) {
+        let roster_team_name = server_data.match_setup.get_team_name(&roster_team);
+        let roster_team_name = if roster_team_name.is_empty() { "Your team".to_string() } else { roster_team_name };
+        return Err(format!("{} plays on the {} side, please switch teams", roster_team_name, expected_side));
+    }
+    return Ok(());
+}
+
+fn veto_command(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, chat: &str, steam_id: &SteamId, player_team: &str) {
+    let split_chat: Vec<&str> = chat.split_whitespace().collect();
+    if split_chat.len() < 2 { return; }
+    let action = match split_chat[0] {
+        ".ban" => { MatchSetupVetoAction::Ban }
+        ".pick" => { MatchSetupVetoAction::Pick }
+        _ => { return; }
+    };
+
+    let veto_setup = match &server_data.match_setup.veto {
+        Some(veto_setup) => { veto_setup.clone() }
+        None => { return; }
+    };
+    let veto_step = match veto_setup.steps.get(server_data.veto.actions.len()) {
+        Some(veto_step) => { veto_step.clone() }
+        None => { return; }
+    };
+
+    let player_veto_team = if veto_setup.team_a_captain.is_some() || veto_setup.team_b_captain.is_some() {
+        if veto_setup.team_a_captain.as_ref() == Some(steam_id) {
+            MatchSetupTeam::TeamA
+        } else if veto_setup.team_b_captain.as_ref() == Some(steam_id) {
+            MatchSetupTeam::TeamB
+        } else {
+            return;
         }
+    } else if player_team.eq_ignore_ascii_case("CT") {
+        MatchSetupTeam::TeamA
+    } else {
+        MatchSetupTeam::TeamB
+    };
+
+    let address = server_data.config.address.clone();
+    if player_veto_team != veto_step.team || action != veto_step.action {
+        announce_veto_step(csctrl, server_data);
         return;
     }
 
-    let mut player_team: String = "".to_string();
-    let player_index = find_player_index_and_team_with_steamid3(server_data, &steam_id, &mut player_team);
-    if player_index > -1 {
+    let requested_map = split_chat[1].to_lowercase();
+    let map_index = server_data.veto.remaining_maps.iter().position(|map| {
+        let map = map.to_lowercase();
+        map == requested_map || map.ends_with(&format!("_{}", requested_map))
+    });
+    let map = match map_index {
+        Some(map_index) => { server_data.veto.remaining_maps.remove(map_index) }
+        None => {
+            csctrl.send_message_to_server_thread(&address, CsctrlServerRequest::Say { message: format!("'{}' is not an available map", split_chat[1]) });
+            return;
+        }
+    };
+
+    let team_name = server_data.match_setup.get_team_name(&player_veto_team);
+    csctrl.send_message_to_server_thread(&address, CsctrlServerRequest::Say { message: format!("{} {} {}", team_name, if action == MatchSetupVetoAction::Ban { "banned" } else { "picked" }, map) });
+    server_data.veto.actions.push(CsctrlVetoAction { team_name, action, map });
+    csctrl.set_data_dirty();
+
+    if server_data.veto.actions.len() < veto_setup.steps.len() && !server_data.veto.remaining_maps.is_empty() {
+        announce_veto_step(csctrl, server_data);
+        return;
+    }
+
+    let mut series_maps: Vec<MatchSetupMap> = server_data.veto.actions.iter()
+        .filter(|veto_action| veto_action.action == MatchSetupVetoAction::Pick)
+        .map(|veto_action| MatchSetupMap { name: veto_action.map.clone(), side: MatchSetupMapSide::Knife })
+        .collect();
+    if server_data.veto.remaining_maps.len() == 1 {
+        series_maps.push(MatchSetupMap { name: server_data.veto.remaining_maps[0].clone(), side: MatchSetupMapSide::Knife });
+    }
+
+    if series_maps.is_empty() {
+        tracing::error!("Veto on server '{}' finished without any map left to play", address);
+        server_data.status = CsctrlMatchStatus::NoHook;
+        return;
+    }
+
+    let map_names: Vec<String> = series_maps.iter().map(|series_map| series_map.name.clone()).collect();
+    csctrl.send_message_to_server_thread(&address, CsctrlServerRequest::Say { message: format!("Veto finished. Maps: {}", map_names.join(", ")) });
+    server_data.series = CsctrlDataSeries {
+        is_active: true,
+        maps: series_maps,
+        ..Default::default()
+    };
+    server_data.status = CsctrlMatchStatus::NoHook;
+    if let Err(error) = csctrl.start_server_match(server_data) {
+        tracing::error!(error);
+    }
+}
+
+pub fn announce_veto_step(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer) {
+    let veto_step = match server_data.match_setup.veto.as_ref().and_then(|veto_setup| veto_setup.steps.get(server_data.veto.actions.len())) {
+        Some(veto_step) => { veto_step.clone() }
+        None => { return; }
+    };
+
+    let team_name = server_data.match_setup.get_team_name(&veto_step.team);
+    let (action_name, action_command) = if veto_step.action == MatchSetupVetoAction::Ban { ("ban", ".ban") } else { ("pick", ".pick") };
+    let say_text = format!("{} to {} a map with '{} <map>': {}", team_name, action_name, action_command, server_data.veto.remaining_maps.join(", "));
+    csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::Say { message: say_text.to_string() });
+}
+
+fn request_pause(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, player_team: &str, pause_type: CsctrlPauseType) {
+    if server_data.status != CsctrlMatchStatus::Live { return; }
+
+    let team = server_data.get_setup_team_for_side(player_team);
+    let team_name = get_team_name_for_side(server_data, player_team);
+    let say_text = if pause_type == CsctrlPauseType::Tactical {
+        let tactical_timeouts_per_team = server_data.match_setup.tactical_timeouts_per_team;
+        let tactical_timeouts_used = server_data.get_tactical_timeouts_used(&team);
+        if tactical_timeouts_used >= tactical_timeouts_per_team {
+            csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::Say { message: format!("{} has no tactical timeouts left", team_name) });
+            return;
+        }
+        format!("{} called a tactical timeout ({}/{})", team_name, tactical_timeouts_used + 1, tactical_timeouts_per_team)
+    } else {
+        format!("{} called a technical pause. Both teams must type '.unpause' to resume", team_name)
+    };
+
+    if !csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::MatchPause { announcement: say_text.clone() }) {
+        return;
+    }
+
+    server_data.pauses.push(CsctrlDataPause {
+        pause_type,
+        team,
+        team_name,
+        round: server_data.rounds_played + 1,
+        // Called during freeze time the pause starts right away, otherwise it waits for the next freeze period
+        started_at: if server_data.is_freeze_period { Some(get_unix_timestamp_secs()) } else { None },
+        ended_at: None,
+        unpause_requested_ct: false,
+        unpause_requested_t: false,
+    });
+    server_data.status = CsctrlMatchStatus::Paused;
+    csctrl.set_data_dirty();
+}
+
+fn request_unpause(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, player_team: &str) {
+    if server_data.status != CsctrlMatchStatus::Paused { return; }
+
+    let team = server_data.get_setup_team_for_side(player_team);
+    let team_name = get_team_name_for_side(server_data, player_team);
+    let address = server_data.config.address.clone();
+    let active_pause = match server_data.get_active_pause() {
+        Some(pause) => { pause }
+        None => { return; }
+    };
+
+    if active_pause.pause_type == CsctrlPauseType::Tactical {
+        // Only the team that called the timeout can cut it short
+        if active_pause.team != team { return; }
+    } else {
         if player_team.eq_ignore_ascii_case("CT") {
-            server_data.team_ct.players.remove(player_index as usize);
-        } else if player_team.eq_ignore_ascii_case("TERRORIST") {
-            server_data.team_t.players.remove(player_index as usize);
+            active_pause.unpause_requested_ct = true;
+        } else {
+            active_pause.unpause_requested_t = true;
+        }
+
+        if !active_pause.unpause_requested_ct || !active_pause.unpause_requested_t {
+            csctrl.send_message_to_server_thread(&address, CsctrlServerRequest::Say { message: format!("{} is ready to resume, waiting for the other team to type '.unpause'", team_name) });
+            csctrl.set_data_dirty();
+            return;
         }
     }
 
-    if team_to.eq_ignore_ascii_case("TERRORIST") {
-        server_data.team_t.players.push(player_data);
-    } else if team_to.eq_ignore_ascii_case("CT") {
-        server_data.team_ct.players.push(player_data);
+    if !csctrl.send_message_to_server_thread(&address, CsctrlServerRequest::MatchUnpause { announcement: "Resuming the match".to_string() }) {
+        return;
+    }
+
+    active_pause.ended_at = Some(get_unix_timestamp_secs());
+    server_data.status = CsctrlMatchStatus::Live;
+    csctrl.set_data_dirty();
+}
+
+fn get_team_name_for_side(server_data: &CsctrlDataServer, side: &str) -> String {
+    let (team_name, side_name) = if side.eq_ignore_ascii_case("CT") {
+        (&server_data.team_ct.name, "CT")
+    } else {
+        (&server_data.team_t.name, "T")
+    };
+
+    if team_name.is_empty() { return side_name.to_string(); }
+    return team_name.to_string();
+}
+
+pub fn round_end(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    let team_side = regex_captures["team_side"].to_string();
+    let ct_score: u8 = regex_captures["ct_score"].parse().unwrap_or(0);
+    let t_score: u8 = regex_captures["t_score"].parse().unwrap_or(0);
+
+    if server_data.status == CsctrlMatchStatus::KnifeRound {
+        // Restarting the game out of warmup can trigger a round end without anyone scoring
+        if ct_score + t_score == 0 { return; }
+
+        let winner_team_name = if team_side.eq_ignore_ascii_case("CT") {
+            server_data.team_ct.name.clone()
+        } else {
+            server_data.team_t.name.clone()
+        };
+
+        if !csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::MatchKnifeEnd { winner_team_name: winner_team_name.clone() }) {
+            return;
+        }
+
+        tracing::info!("Team '{}' won the knife round on the {} side", winner_team_name, team_side);
+        server_data.knife_winner_side = team_side;
+        server_data.status = CsctrlMatchStatus::SwitchTeamsWarmup;
+        csctrl.set_data_dirty();
+        return;
+    }
+
+    if !is_match_in_progress(server_data) { return; }
+
+    server_data.team_ct.score = ct_score;
+    server_data.team_t.score = t_score;
+    server_data.rounds_played = ct_score + t_score;
+    server_data.last_round_winner_side = team_side;
+    server_data.last_round_reason = regex_captures["reason"].replace("SFUI_Notice_", "");
+
+    // Sides are only swapped once the next freeze period starts, the round end lines still report the old sides
+    if server_data.is_halftime() {
+        server_data.is_side_swap_pending = true;
+    }
+
+    broadcast_event(&server_data.config.address, CsctrlEvent::RoundEnd {
+        round: server_data.rounds_played,
+        winner_side: server_data.last_round_winner_side.clone(),
+        reason: server_data.last_round_reason.clone(),
+    });
+    csctrl.set_data_dirty();
+}
+
+pub fn team_scored(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    if !is_match_in_progress(server_data) { return; }
+
+    let score: u8 = regex_captures["score"].parse().unwrap_or(0);
+    if regex_captures["team_side"].eq_ignore_ascii_case("CT") {
+        server_data.team_ct.score = score;
+    } else {
+        server_data.team_t.score = score;
+    }
+
+    csctrl.set_data_dirty();
+}
+
+pub fn match_status_score(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    server_data.map = regex_captures["map"].to_string();
+    csctrl.set_data_dirty();
+
+    if !is_match_in_progress(server_data) { return; }
+
+    let rounds_played: i16 = regex_captures["rounds_played"].parse().unwrap_or(-1);
+    if rounds_played < 0 { return; }
+
+    server_data.team_ct.score = regex_captures["ct_score"].parse().unwrap_or(0);
+    server_data.team_t.score = regex_captures["t_score"].parse().unwrap_or(0);
+    server_data.rounds_played = rounds_played as u8;
+}
+
+pub fn freeze_period_start(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer) {
+    for player_stats in server_data.player_stats.values_mut() {
+        player_stats.round_health = 100;
+    }
+
+    server_data.is_freeze_period = true;
+
+    // mp_pause_match only kicks in once the next freeze period starts, so that is when a pause begins counting
+    if let Some(active_pause) = server_data.get_active_pause() {
+        if active_pause.started_at.is_none() {
+            active_pause.started_at = Some(get_unix_timestamp_secs());
+            csctrl.set_data_dirty();
+        }
+    }
+
+    if !server_data.is_side_swap_pending { return; }
+
+    server_data.is_side_swap_pending = false;
+    std::mem::swap(&mut server_data.team_ct.setup_team, &mut server_data.team_t.setup_team);
+    std::mem::swap(&mut server_data.team_ct.name, &mut server_data.team_t.name);
+    std::mem::swap(&mut server_data.team_ct.score, &mut server_data.team_t.score);
+    tracing::info!("Halftime on server '{}', teams swapped sides", server_data.config.address);
+
+    broadcast_event(&server_data.config.address, CsctrlEvent::Halftime {
+        team_ct_name: server_data.team_ct.name.clone(),
+        team_ct_score: server_data.team_ct.score,
+        team_t_name: server_data.team_t.name.clone(),
+        team_t_score: server_data.team_t.score,
+    });
+    csctrl.set_data_dirty();
+}
+
+pub fn round_start(server_data: &mut CsctrlDataServer) {
+    server_data.is_freeze_period = false;
+}
+
+pub fn game_over(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    if !is_match_in_progress(server_data) { return; }
+
+    let finished_at = get_unix_timestamp_secs();
+    if let Some(active_pause) = server_data.get_active_pause() {
+        active_pause.ended_at = Some(finished_at);
+    }
+
+    server_data.map = regex_captures["map"].to_string();
+    server_data.team_ct.score = regex_captures["ct_score"].parse().unwrap_or(server_data.team_ct.score);
+    server_data.team_t.score = regex_captures["t_score"].parse().unwrap_or(server_data.team_t.score);
+    server_data.status = CsctrlMatchStatus::Finished;
+    csctrl.set_data_dirty();
+
+    let team_ct_name = get_team_name_for_side(server_data, "CT");
+    let team_t_name = get_team_name_for_side(server_data, "TERRORIST");
+    let winner = if server_data.team_ct.score > server_data.team_t.score {
+        team_ct_name.clone()
+    } else if server_data.team_t.score > server_data.team_ct.score {
+        team_t_name.clone()
+    } else {
+        "".to_string()
+    };
+
+    let duration_secs = if server_data.match_started_at > 0 {
+        finished_at - server_data.match_started_at
+    } else {
+        regex_captures["duration_mins"].parse::<u64>().unwrap_or(0) * 60
+    };
+
+    let match_result = CsctrlMatchResult {
+        server_address: server_data.config.address.clone(),
+        map: server_data.map.clone(),
+        winner: winner.clone(),
+        teams: vec![
+            CsctrlMatchResultTeam { name: team_ct_name.clone(), side: "CT".to_string(), score: server_data.team_ct.score },
+            CsctrlMatchResultTeam { name: team_t_name.clone(), side: "TERRORIST".to_string(), score: server_data.team_t.score },
+        ],
+        rounds_played: server_data.team_ct.score + server_data.team_t.score,
+        started_at: finished_at - duration_secs,
+        finished_at,
+        duration_secs,
+        players: server_data.player_stats.values().map(|stats| CsctrlMatchResultPlayer {
+            steam_id: stats.steam_id,
+            steam_id_64: stats.steam_id.to_steam_id_64(),
+            stats: stats.clone(),
+            average_damage_per_round: stats.average_damage_per_round(server_data.rounds_played),
+            headshot_percentage: stats.headshot_percentage(),
+            kill_death_ratio: stats.kill_death_ratio(),
+        }).collect(),
+        pauses: server_data.pauses.clone(),
+        veto: server_data.veto.actions.clone(),
+    };
+
+    let match_result_name = format!("{}-vs-{}_{}", team_ct_name, team_t_name, server_data.map);
+    match write_match_log(&match_result_name, &match_result) {
+        Ok(path) => { tracing::info!("Match on server '{}' finished. Result written to '{}'", server_data.config.address, path.to_str().unwrap()); }
+        Err(error) => { tracing::error!(error); }
+    }
+    broadcast_event(&server_data.config.address, CsctrlEvent::MatchFinished { result: match_result });
+
+    let announcement = if winner.is_empty() {
+        format!("The match ended in a draw {}:{}", server_data.team_ct.score, server_data.team_t.score)
+    } else {
+        format!("{} wins the match {}:{}!", winner, cmp::max(server_data.team_ct.score, server_data.team_t.score), cmp::min(server_data.team_ct.score, server_data.team_t.score))
+    };
+
+    if server_data.series.is_active {
+        series_map_finished(csctrl, server_data, &winner, &announcement);
+        return;
+    }
+
+    csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::MatchEnd { announcement });
+}
+
+/// Names can be empty or shared, so the map is credited to the match setup team playing each side
+fn series_map_finished(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, winner: &str, announcement: &str) {
+    let team_a_name = server_data.match_setup.team_a_name.clone();
+    let team_b_name = server_data.match_setup.team_b_name.clone();
+    let (team_a_score, team_b_score) = if server_data.team_ct.setup_team == MatchSetupTeam::TeamA {
+        (server_data.team_ct.score, server_data.team_t.score)
+    } else {
+        (server_data.team_t.score, server_data.team_ct.score)
+    };
+
+    if team_a_score > team_b_score {
+        server_data.series.team_a_maps_won += 1;
+    } else if team_b_score > team_a_score {
+        server_data.series.team_b_maps_won += 1;
+    }
+
+    server_data.series.map_results.push(CsctrlSeriesMapResult {
+        map: server_data.map.clone(),
+        winner: winner.to_string(),
+        team_a_score,
+        team_b_score,
+    });
+
+    let series_score = format!("{} {}:{} {}", team_a_name, server_data.series.team_a_maps_won, server_data.series.team_b_maps_won, team_b_name);
+    let is_last_map = server_data.series.map_index + 1 >= server_data.series.maps.len();
+    if !server_data.series.is_clinched() && !is_last_map {
+        server_data.series.map_index += 1;
+        let next_map = server_data.series.maps[server_data.series.map_index].name.clone();
+        let server_request = CsctrlServerRequest::SeriesNext {
+            map: next_map.clone(),
+            announcement: format!("{} Series: {}. Next map: {}", announcement, series_score, next_map),
+        };
+        if !csctrl.send_message_to_server_thread(&server_data.config.address, server_request) {
+            return;
+        }
+
+        server_data.status = CsctrlMatchStatus::ChangingMap;
+        csctrl.set_data_dirty();
+        return;
+    }
+
+    let series_winner = if server_data.series.team_a_maps_won > server_data.series.team_b_maps_won {
+        team_a_name.clone()
+    } else if server_data.series.team_b_maps_won > server_data.series.team_a_maps_won {
+        team_b_name.clone()
+    } else {
+        "".to_string()
+    };
+
+    let series_result = CsctrlSeriesResult {
+        server_address: server_data.config.address.clone(),
+        team_a_name: team_a_name.clone(),
+        team_b_name: team_b_name.clone(),
+        team_a_maps_won: server_data.series.team_a_maps_won,
+        team_b_maps_won: server_data.series.team_b_maps_won,
+        winner: series_winner.clone(),
+        maps: server_data.series.map_results.clone(),
+        veto: server_data.veto.actions.clone(),
+    };
+
+    match write_match_log(&format!("{}-vs-{}_series", team_a_name, team_b_name), &series_result) {
+        Ok(path) => { tracing::info!("Series on server '{}' finished. Result written to '{}'", server_data.config.address, path.to_str().unwrap()); }
+        Err(error) => { tracing::error!(error); }
+    }
+
+    server_data.series.is_active = false;
+    csctrl.set_data_dirty();
+
+    let series_announcement = if series_winner.is_empty() {
+        format!("{} The series ended in a draw {}", announcement, series_score)
+    } else {
+        format!("{} {} wins the series {}", announcement, series_winner, series_score)
+    };
+    csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::MatchEnd { announcement: series_announcement });
+}
+
+pub fn map_started(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    server_data.map = regex_captures["map"].to_string();
+    csctrl.set_data_dirty();
+
+    // Map changes and server restarts can drop the log address so it is applied again on every map start
+    csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::RegisterLogAddress);
+
+    if server_data.status != CsctrlMatchStatus::ChangingMap { return; }
+    csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::MatchStart);
+}
+
+pub fn server_cvar(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    let value = regex_captures["value"].parse::<u8>();
+    if value.is_err() { return; }
+
+    match &regex_captures["cvar"] {
+        "mp_maxrounds" => { server_data.max_rounds = value.unwrap(); }
+        "mp_overtime_maxrounds" => { server_data.overtime_max_rounds = value.unwrap(); }
+        _ => { return; }
+    }
+
+    csctrl.set_data_dirty();
+}
+
+pub fn player_connected(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    let steam_id = match parse_steam_id(&regex_captures["steam_id"]) {
+        Some(steam_id) => { steam_id }
+        None => { return; }
+    };
+    let player_username = regex_captures["username"].to_string();
+    let address = regex_captures["address"].to_string();
+
+    let user_id: u16 = regex_captures["user_id"].parse().unwrap_or(0);
+    let is_roster_member = server_data.match_setup.get_roster_team(&steam_id).is_some();
+    if server_data.match_setup.has_every_roster() && !is_roster_member && !steam_id.is_bot() {
+        tracing::info!("Kicking '{}' ({}) from server '{}' since they are not on any team roster", player_username, steam_id, server_data.config.address);
+        csctrl.send_message_to_server_thread(&server_data.config.address, CsctrlServerRequest::Kick { user_id, reason: "You are not on any team roster".to_string() });
+    }
+
+    server_data.connections.insert(get_player_key(&steam_id, &player_username), CsctrlDataConnection {
+        name: player_username,
+        steam_id,
+        user_id,
+        state: CsctrlConnectionState::Connected,
+        address: if address == "none" { "".to_string() } else { address },
+        disconnect_reason: "".to_string(),
+    });
+
+    csctrl.set_data_dirty();
+}
+
+pub fn player_connection_state(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures, state: CsctrlConnectionState) {
+    let steam_id = match parse_steam_id(&regex_captures["steam_id"]) {
+        Some(steam_id) => { steam_id }
+        None => { return; }
+    };
+    let player_username = regex_captures["username"].to_string();
+
+    // Players that were already connected when csctrl started listening never logged a 'connected' line
+    let connection = server_data.connections.entry(get_player_key(&steam_id, &player_username)).or_insert(CsctrlDataConnection {
+        name: player_username.clone(),
+        steam_id,
+        user_id: 0,
+        state: CsctrlConnectionState::Connected,
+        address: "".to_string(),
+        disconnect_reason: "".to_string(),
+    });
+    connection.name = player_username;
+    connection.user_id = regex_captures["user_id"].parse().unwrap_or(connection.user_id);
+    connection.disconnect_reason = "".to_string();
+
+    // A validation can arrive after the player already entered the game and must not demote them
+    if !(state == CsctrlConnectionState::Validated && connection.state == CsctrlConnectionState::InGame) {
+        connection.state = state;
+    }
+
+    csctrl.set_data_dirty();
+}
+
+pub fn player_disconnected(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    let steam_id = match parse_steam_id(&regex_captures["steam_id"]) {
+        Some(steam_id) => { steam_id }
+        None => { return; }
+    };
+    let player_username = regex_captures["username"].to_string();
+    let player_key = get_player_key(&steam_id, &player_username);
+
+    remove_player_from_teams(server_data, &steam_id);
+
+    if steam_id.is_bot() {
+        server_data.connections.remove(&player_key);
+    } else {
+        let connection = server_data.connections.entry(player_key).or_insert(CsctrlDataConnection {
+            name: player_username.clone(),
+            steam_id,
+            user_id: 0,
+            state: CsctrlConnectionState::Disconnected,
+            address: "".to_string(),
+            disconnect_reason: "".to_string(),
+        });
+        connection.name = player_username;
+        connection.state = CsctrlConnectionState::Disconnected;
+        connection.disconnect_reason = regex_captures["reason"].to_string();
+    }
+
+    csctrl.set_data_dirty();
+}
+
+fn remove_player_from_teams(server_data: &mut CsctrlDataServer, steam_id: &SteamId) {
+    let mut player_team: String = "".to_string();
+    let player_index = find_player_index_and_team_with_steam_id(server_data, steam_id, &mut player_team);
+    if player_index < 0 { return; }
+
+    let removed_player = if player_team.eq_ignore_ascii_case("CT") {
+        server_data.team_ct.players.remove(player_index as usize)
+    } else {
+        server_data.team_t.players.remove(player_index as usize)
+    };
+
+    if removed_player.is_ready {
+        server_data.player_ready_amount -= 1;
+    }
+}
+
+pub fn player_killed(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    if !is_match_in_progress(server_data) { return; }
+
+    let is_team_kill = regex_captures["attacker_team"] == regex_captures["victim_team"];
+    let is_headshot = regex_captures.name("flags").is_some_and(|flags| flags.as_str().contains("headshot"));
+
+    if let Some(victim_stats) = get_player_stats(server_data, &regex_captures["victim_steam_id"], &regex_captures["victim_name"]) {
+        victim_stats.deaths += 1;
+    }
+
+    if !is_team_kill {
+        if let Some(attacker_stats) = get_player_stats(server_data, &regex_captures["attacker_steam_id"], &regex_captures["attacker_name"]) {
+            attacker_stats.kills += 1;
+            if is_headshot { attacker_stats.headshot_kills += 1; }
+        }
+    }
+
+    if let (Some(attacker_steam_id), Some(victim_steam_id)) = (parse_steam_id(&regex_captures["attacker_steam_id"]), parse_steam_id(&regex_captures["victim_steam_id"])) {
+        broadcast_event(&server_data.config.address, CsctrlEvent::Kill {
+            attacker_name: regex_captures["attacker_name"].to_string(),
+            attacker_steam_id,
+            victim_name: regex_captures["victim_name"].to_string(),
+            victim_steam_id,
+            weapon: regex_captures["weapon"].to_string(),
+            is_headshot,
+        });
+    }
+
+    csctrl.set_data_dirty();
+}
+
+/// Falling, the bomb and your own grenade still count as a death, just without anyone getting the kill
+pub fn player_suicide(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    if !is_match_in_progress(server_data) { return; }
+
+    let player_stats = match get_player_stats(server_data, &regex_captures["steam_id"], &regex_captures["username"]) {
+        Some(player_stats) => { player_stats }
+        None => { return; }
+    };
+    player_stats.deaths += 1;
+    player_stats.round_health = 0;
+
+    csctrl.set_data_dirty();
+}
+
+pub fn player_assisted(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    if !is_match_in_progress(server_data) { return; }
+    if regex_captures["attacker_team"] == regex_captures["victim_team"] { return; }
+    if &regex_captures["assist_type"] != "assisted" { return; }
+
+    let attacker_stats = match get_player_stats(server_data, &regex_captures["attacker_steam_id"], &regex_captures["attacker_name"]) {
+        Some(attacker_stats) => { attacker_stats }
+        None => { return; }
+    };
+    attacker_stats.assists += 1;
+
+    csctrl.set_data_dirty();
+}
+
+pub fn player_attacked(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, regex_captures: &Captures) {
+    if !is_match_in_progress(server_data) { return; }
+
+    let damage: u32 = regex_captures["damage"].parse().unwrap_or(0);
+    let remaining_health: Option<u32> = regex_captures.name("health").and_then(|health| health.as_str().parse().ok());
+
+    // The logged damage includes overkill, so a hit only counts up to the health the victim had before it.
+    // Remaining health is logged as 0 on a kill, so that hit falls back to the health tracked through the round
+    let victim_stats = match get_player_stats(server_data, &regex_captures["victim_steam_id"], &regex_captures["victim_name"]) {
+        Some(victim_stats) => { victim_stats }
+        None => { return; }
+    };
+    let health_before_hit = match remaining_health {
+        Some(remaining_health) if remaining_health > 0 => { remaining_health + damage }
+        _ => { victim_stats.round_health as u32 }
+    };
+    let dealt_damage = damage.min(health_before_hit);
+    victim_stats.round_health = remaining_health.unwrap_or(health_before_hit - dealt_damage).min(u8::MAX as u32) as u8;
+
+    // Team damage still lowers the victim's health but doesn't count towards the attacker's damage
+    if regex_captures["attacker_team"] == regex_captures["victim_team"] { return; }
+
+    let attacker_stats = match get_player_stats(server_data, &regex_captures["attacker_steam_id"], &regex_captures["attacker_name"]) {
+        Some(attacker_stats) => { attacker_stats }
+        None => { return; }
+    };
+    attacker_stats.damage += dealt_damage;
+
+    csctrl.set_data_dirty();
+}
+
+/// Bots share the 'BOT' steam id so they are told apart by name instead
+pub fn get_player_key(steam_id: &SteamId, name: &str) -> String {
+    if steam_id.is_bot() { return format!("BOT:{}", name); }
+    return steam_id.to_string();
+}
+
+fn parse_steam_id(steam_id: &str) -> Option<SteamId> {
+    return match SteamId::from_str(steam_id) {
+        Ok(steam_id) => { Some(steam_id) }
+        Err(error) => {
+            tracing::error!(error);
+            None
+        }
+    };
+}
+
+fn get_player_stats<'a>(server_data: &'a mut CsctrlDataServer, steam_id: &str, name: &str) -> Option<&'a mut CsctrlDataPlayerStats> {
+    let steam_id = parse_steam_id(steam_id)?;
+    let player_stats = server_data.player_stats.entry(get_player_key(&steam_id, name)).or_insert_with(|| CsctrlDataPlayerStats::new(steam_id, name));
+    player_stats.name = name.to_string();
+    return Some(player_stats);
+}
+
+fn is_match_in_progress(server_data: &CsctrlDataServer) -> bool {
+    return server_data.status == CsctrlMatchStatus::Live || server_data.status == CsctrlMatchStatus::Paused;
+}
+
+fn knife_side_choice(csctrl: &mut Csctrl, server_data: &mut CsctrlDataServer, chat: &str, player_team: &str) {
+    if !player_team.eq_ignore_ascii_case(&server_data.knife_winner_side) { return; }
+
+    let stay_command = chat.trim() == ".stay";
+    let switch_command = chat.trim() == ".switch";
+    if stay_command == switch_command { return; }
+
+    let server_request = if switch_command { CsctrlServerRequest::MatchSwitch } else { CsctrlServerRequest::MatchStay };
+    if !csctrl.send_message_to_server_thread(&server_data.config.address, server_request) {
+        return;
+    }
+
+    if switch_command {
+        std::mem::swap(&mut server_data.team_ct.setup_team, &mut server_data.team_t.setup_team);
+        std::mem::swap(&mut server_data.team_ct.name, &mut server_data.team_t.name);
     }
 
+    server_data.status = CsctrlMatchStatus::Live;
     csctrl.set_data_dirty();
 }
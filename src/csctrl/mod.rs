@@ -1,4 +1,6 @@
 pub mod csctrl;
 pub mod types;
 pub mod server;
-pub mod log_events;
\ No newline at end of file
+pub mod log_events;
+pub mod steam_id;
+pub mod server_status;
\ No newline at end of file
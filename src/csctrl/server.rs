@@ -1,96 +1,151 @@
-use std::collections::{HashMap, VecDeque};
-use tokio::runtime::Runtime;
-use tokio::sync::mpsc::error::TryRecvError;
-use crate::csctrl::csctrl::{FORMAT_SEPARATOR, get_data, get_static_data};
-use crate::csctrl::types::{CsctrlLogType, CsctrlServerSetup, MatchSetup};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use crate::csctrl::csctrl::{get_data, get_static_data};
+use crate::csctrl::server_status::parse_server_status;
+use crate::csctrl::types::{CsctrlLogType, CsctrlMatchStatus, CsctrlMessage, CsctrlServerReply, CsctrlServerRequest, CsctrlServerSetup, MatchSetup};
 use crate::rcon::connection::RconConnection;
 use crate::system::utilities::get_csctrl_config_file_path;
 
+const LIVE_RESTART_SEQUENCE: [u8; 3] = [1, 1, 3];
+const MATCH_START_COUNTDOWN_SECS: u8 = 3;
+const SERIES_MAP_CHANGE_DELAY_SECS: u64 = 15;
+const LOG_ADDRESS_RETRY_SECS: u64 = 10;
+const HEALTH_CHECK_INTERVAL_SECS: u64 = 10;
+const MAINTENANCE_INTERVAL_SECS: u64 = 1;
+const KNIFE_ROUND_COMMANDS: [&str; 10] = [
+    "mp_ct_default_primary \"\"",
+    "mp_t_default_primary \"\"",
+    "mp_ct_default_secondary \"\"",
+    "mp_t_default_secondary \"\"",
+    "mp_ct_default_grenades \"\"",
+    "mp_t_default_grenades \"\"",
+    "mp_give_player_c4 0",
+    "mp_startmoney 0",
+    "mp_afterroundmoney 0",
+    "mp_free_armor 1",
+];
+/// Competitive defaults for every cvar the knife round changes. They run before the match cfg so it can still override them
+const KNIFE_ROUND_RESTORE_COMMANDS: [&str; 10] = [
+    "mp_ct_default_primary \"\"",
+    "mp_t_default_primary \"\"",
+    "mp_ct_default_secondary \"weapon_hkp2000\"",
+    "mp_t_default_secondary \"weapon_glock\"",
+    "mp_ct_default_grenades \"\"",
+    "mp_t_default_grenades \"\"",
+    "mp_give_player_c4 1",
+    "mp_startmoney 800",
+    "mp_afterroundmoney 0",
+    "mp_free_armor 0",
+];
+
 pub struct CsctrlServer {
     address: String,
     rcon_connection: crate::rcon::connection::RconConnection,
-    thread_receiver: tokio::sync::mpsc::UnboundedReceiver<String>,
-    thread_sender: tokio::sync::mpsc::UnboundedSender<String>,
+    thread_sender: tokio::sync::mpsc::UnboundedSender<CsctrlMessage>,
     last_rcon_success: bool,
+    /// None when csctrl has no address the server can reach, so the log address is never registered
+    cs_log_url: Option<String>,
+    is_log_address_registered: bool,
+    last_log_address_attempt: Option<Instant>,
+    /// Rcon connection the log address was registered on. A new connection means the server may have restarted and lost it
+    log_address_connection_count: u32,
+    last_health_check: Option<Instant>,
+    last_status_map: Option<String>,
+    /// Map to change to once the deadline passes, so the delay doesn't hold back other requests
+    pending_map_change: Option<(tokio::time::Instant, String)>,
 }
 
 impl CsctrlServer {
-    pub fn csctrl_server(setup: CsctrlServerSetup, sender: tokio::sync::mpsc::UnboundedSender<String>, receiver: tokio::sync::mpsc::UnboundedReceiver<String>) -> CsctrlServer {
+    pub fn csctrl_server(setup: CsctrlServerSetup, cs_log_url: Option<String>, sender: tokio::sync::mpsc::UnboundedSender<CsctrlMessage>) -> CsctrlServer {
         CsctrlServer {
             address: setup.address.to_string(),
             rcon_connection: RconConnection::create_rcon_connection(&setup.address, &setup.rcon_password),
-            thread_receiver: receiver,
             thread_sender: sender,
             last_rcon_success: false,
+            cs_log_url,
+            is_log_address_registered: false,
+            last_log_address_attempt: None,
+            log_address_connection_count: 0,
+            last_health_check: None,
+            last_status_map: None,
+            pending_map_change: None,
         }
     }
 
-    pub fn main(&mut self) {
-        tracing::debug!("Thread created");
+    /// Waits for requests from the main thread while timers drive pending map changes, log address retries, health checks and keepalives
+    pub async fn run(mut self, mut thread_receiver: tokio::sync::mpsc::UnboundedReceiver<CsctrlServerRequest>) {
+        tracing::debug!("Server actor created");
 
+        let mut maintenance_interval = tokio::time::interval(Duration::from_secs(MAINTENANCE_INTERVAL_SECS));
+        maintenance_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
         loop {
-            if !self.tick() { break; };
+            let map_change_deadline = self.pending_map_change.as_ref().map(|(deadline, _)| *deadline);
+            tokio::select! {
+                request = thread_receiver.recv() => {
+                    match request {
+                        Some(request) => { self.handle_thread_message(request).await; }
+                        None => {
+                            tracing::debug!("Main thread dropped its sender. Closing server actor");
+                            break;
+                        }
+                    }
+                }
+                _ = maintenance_interval.tick() => { self.run_maintenance().await; }
+                _ = tokio::time::sleep_until(map_change_deadline.unwrap_or_else(tokio::time::Instant::now)), if map_change_deadline.is_some() => {
+                    self.change_pending_map().await;
+                }
+            }
         }
 
-        tracing::debug!("Thread shutting down");
+        tracing::debug!("Server actor shutting down");
     }
 
-    pub fn tick(&mut self) -> bool{
-        match self.thread_receiver.try_recv() {
-            Ok(message) => { self.handle_thread_message(message); }
-            Err(error) => {
-                if error == TryRecvError::Disconnected {
-                    tracing::error!("Thread has no sender counterpart and has detached. Closing itself");
-                    return false;
-                }
+    async fn run_maintenance(&mut self) {
+        if self.is_log_address_registered && self.rcon_connection.get_connection_count() != self.log_address_connection_count {
+            tracing::info!("Rcon reconnected to the server. Registering the log address again");
+            self.is_log_address_registered = false;
+        }
 
-                if error == TryRecvError::Empty {
-                    return true;
-                }
+        let is_log_address_retry_due = self.last_log_address_attempt.is_none_or(|last_attempt| last_attempt.elapsed().as_secs() >= LOG_ADDRESS_RETRY_SECS);
+        if self.cs_log_url.is_some() && !self.is_log_address_registered && is_log_address_retry_due {
+            self.register_log_address().await;
+        }
 
-                tracing::error!("Can't receive message from main thread. Error: {}", error);
-            }
+        let is_health_check_due = self.last_health_check.is_none_or(|last_check| last_check.elapsed().as_secs() >= HEALTH_CHECK_INTERVAL_SECS);
+        if is_health_check_due {
+            self.check_health().await;
         }
-        return true;
-    }
 
-    fn handle_thread_message(&mut self, message: String) {
-        tracing::trace!("Received message: '{}'", message);
-        let mut split_string: VecDeque<&str> = message.split(" ").collect();
-        let first_word = split_string[0];
-        match first_word {
-            "rcon" => {
-                split_string.pop_front().unwrap();
-                let mut arguments = "".to_string();
-                for word in split_string {
-                    arguments.push_str(format!("{} ", word).as_str());
-                }
+        self.rcon_connection.keepalive().await;
+    }
 
-                Runtime::new().unwrap().block_on(self.rcon(arguments.trim().to_string()));
+    async fn handle_thread_message(&mut self, request: CsctrlServerRequest) {
+        tracing::trace!("Received request: '{:?}'", request);
+        match request {
+            CsctrlServerRequest::Rcon { request_id, command } => {
+                let result = self.rcon_with_response(command).await;
+                self.send_reply_to_main_thread(CsctrlServerReply::RconResult { request_id, result });
             }
-            "server.match.start" => {
-                let data = get_data().read().unwrap();
-                let server_data = data.servers.get(&self.address).unwrap();
-
+            CsctrlServerRequest::MatchStart => {
+                // The data lock can't be held while waiting on the server
+                let (team_ct_name, team_t_name, cfg_filename) = {
+                    let data = get_data().read().unwrap();
+                    let server_data = data.servers.get(&self.address).unwrap();
+                    let (team_ct_name, team_t_name) = server_data.get_starting_team_names();
+                    (team_ct_name, team_t_name, server_data.match_setup.cfg_filename.clone())
+                };
                 let mut cmd_vec = vec![
                     self.generate_say_command("Loading match..."),
-                    format!("mp_teamname_1 \"{}\"", server_data.match_setup.team_a_name),
-                    format!("mp_teamname_2 \"{}\"", server_data.match_setup.team_b_name)
+                    format!("mp_teamname_1 \"{}\"", team_ct_name),
+                    format!("mp_teamname_2 \"{}\"", team_t_name)
                 ];
 
-                let mut match_cfg_path = get_csctrl_config_file_path();
-                match_cfg_path.pop();
-                match_cfg_path.push(format!("cfg/{}.cfg", &server_data.match_setup.cfg_filename));
-
-                let match_cfg_string = std::fs::read_to_string(match_cfg_path);
-                if match_cfg_string.is_err() {
-                    tracing::error!("Error reading match cfg file '{}'", &server_data.match_setup.cfg_filename);
-                    return;
-                }
-                let fixed_line_endings_split_cfg = match_cfg_string.unwrap().replace("\r\n", "\n");
-                let split_cfg: Vec<&str> = fixed_line_endings_split_cfg.split("\n").collect();
-                for split_cmd in split_cfg {
-                    cmd_vec.push(split_cmd.to_string());
+                match self.get_match_cfg_commands(&cfg_filename) {
+                    Ok(cfg_commands) => { cmd_vec.extend(cfg_commands); }
+                    Err(error) => {
+                        tracing::error!(error);
+                        return;
+                    }
                 }
 
                 cmd_vec.push("mp_warmup_pausetimer 1".to_string());
@@ -99,26 +154,240 @@ impl CsctrlServer {
                 cmd_vec.push(self.generate_say_command("Type '.ready' or '.unready' to change your readiness status"));
 
                 for cmd in cmd_vec {
-                    Runtime::new().unwrap().block_on(self.rcon(cmd));
+                    let _ = self.rcon_with_response(cmd).await;
                 }
 
                 if !self.last_rcon_success {
                     return;
                 }
 
-                self.send_message_to_main_thread("CsctrlMatchStatus:PreMatchWarmup");
+                self.send_match_status_to_main_thread(CsctrlMatchStatus::PreMatchWarmup);
+            }
+            CsctrlServerRequest::RegisterLogAddress => {
+                self.register_log_address().await;
+            }
+            CsctrlServerRequest::MatchKnife => {
+                self.start_knife_round().await;
+            }
+            CsctrlServerRequest::MatchLive => {
+                self.start_live_match(CsctrlMatchStatus::PreMatchWarmup).await;
+            }
+            CsctrlServerRequest::MatchKnifeEnd { winner_team_name } => {
+                self.end_knife_round(&winner_team_name).await;
+            }
+            CsctrlServerRequest::MatchPause { announcement } => {
+                self.set_match_paused(&announcement, true).await;
+            }
+            CsctrlServerRequest::MatchUnpause { announcement } => {
+                self.set_match_paused(&announcement, false).await;
+            }
+            CsctrlServerRequest::MatchEnd { announcement } => {
+                let cmd_vec = vec![
+                    self.generate_say_command(&announcement),
+                    self.generate_say_command("Thanks for playing!"),
+                ];
+                self.rcon_batch(cmd_vec).await;
+
+                // The match is over whether or not the announcement made it through
+                self.pending_map_change = None;
+                self.send_match_status_to_main_thread(CsctrlMatchStatus::NoHook);
+            }
+            CsctrlServerRequest::ChangeLevel { map } => {
+                self.pending_map_change = None;
+                if !self.rcon_batch(vec![format!("changelevel {}", map)]).await {
+                    self.send_match_status_to_main_thread(CsctrlMatchStatus::NoHook);
+                }
+            }
+            CsctrlServerRequest::SeriesNext { map, announcement } => {
+                self.rcon_batch(vec![self.generate_say_command(&announcement)]).await;
+
+                // Give players a moment to look at the final scoreboard before the map changes
+                let map_change_deadline = tokio::time::Instant::now() + Duration::from_secs(SERIES_MAP_CHANGE_DELAY_SECS);
+                self.pending_map_change = Some((map_change_deadline, map));
+            }
+            CsctrlServerRequest::Kick { user_id, reason } => {
+                self.rcon_batch(vec![format!("kickid {} \"{}\"", user_id, reason)]).await;
+            }
+            CsctrlServerRequest::Say { message } => {
+                let say_command = self.generate_say_command(&message);
+                self.rcon_batch(vec![say_command]).await;
+            }
+            CsctrlServerRequest::MatchStay | CsctrlServerRequest::MatchSwitch => {
+                let match_cfg_filename = get_data().read().unwrap().servers.get(&self.address).unwrap().match_setup.cfg_filename.clone();
+                let cfg_commands = match self.get_match_cfg_commands(&match_cfg_filename) {
+                    Ok(cfg_commands) => { cfg_commands }
+                    Err(error) => {
+                        tracing::error!(error);
+                        self.send_match_status_to_main_thread(CsctrlMatchStatus::SwitchTeamsWarmup);
+                        return;
+                    }
+                };
+                let mut cmd_vec: Vec<String> = KNIFE_ROUND_RESTORE_COMMANDS.iter().map(|restore_command| restore_command.to_string()).collect();
+                cmd_vec.extend(cfg_commands);
+                if matches!(request, CsctrlServerRequest::MatchSwitch) {
+                    cmd_vec.push("mp_swapteams".to_string());
+                }
+
+                if !self.rcon_batch(cmd_vec).await {
+                    self.send_match_status_to_main_thread(CsctrlMatchStatus::SwitchTeamsWarmup);
+                    return;
+                }
+                self.start_live_match(CsctrlMatchStatus::SwitchTeamsWarmup).await;
+            }
+        }
+    }
+
+    async fn change_pending_map(&mut self) {
+        let map = match self.pending_map_change.take() {
+            Some((_, map)) => { map }
+            None => { return; }
+        };
+        if !self.rcon_batch(vec![format!("changelevel {}", map)]).await {
+            self.send_match_status_to_main_thread(CsctrlMatchStatus::NoHook);
+        }
+    }
+
+    async fn set_match_paused(&mut self, announcement: &str, is_pause: bool) {
+        let cmd_vec = vec![
+            self.generate_say_command(announcement),
+            if is_pause { "mp_pause_match".to_string() } else { "mp_unpause_match".to_string() },
+        ];
+
+        if !self.rcon_batch(cmd_vec).await {
+            self.send_match_status_to_main_thread(if is_pause { CsctrlMatchStatus::Live } else { CsctrlMatchStatus::Paused });
+        }
+    }
+
+    async fn start_knife_round(&mut self) {
+        let mut cmd_vec = vec![
+            self.generate_say_command("All players are ready"),
+            "mp_warmup_pausetimer 0".to_string(),
+            "mp_warmup_end".to_string(),
+        ];
+        cmd_vec.extend(KNIFE_ROUND_COMMANDS.iter().map(|knife_command| knife_command.to_string()));
+        if !self.rcon_batch(cmd_vec).await {
+            self.send_match_status_to_main_thread(CsctrlMatchStatus::PreMatchWarmup);
+            return;
+        }
+
+        self.say_countdown("Knife round starting in").await;
+        let cmd_vec = vec![
+            "mp_restartgame 1".to_string(),
+            self.generate_say_command("KNIFE!"),
+        ];
+        if !self.rcon_batch(cmd_vec).await {
+            self.send_match_status_to_main_thread(CsctrlMatchStatus::PreMatchWarmup);
+            return;
+        }
+
+        self.send_match_status_to_main_thread(CsctrlMatchStatus::KnifeRound);
+    }
+
+    async fn end_knife_round(&mut self, winner_team_name: &str) {
+        let cmd_vec = vec![
+            "mp_warmup_start".to_string(),
+            "mp_warmup_pausetimer 1".to_string(),
+            self.generate_say_command(&format!("{} won the knife round!", winner_team_name)),
+            self.generate_say_command("Type '.stay' to keep your side or '.switch' to swap sides"),
+        ];
+        self.rcon_batch(cmd_vec).await;
+    }
+
+    /// `fallback_status` is reported back to the main thread when any step fails so the match can be retried
+    async fn start_live_match(&mut self, fallback_status: CsctrlMatchStatus) {
+        let cmd_vec = vec![
+            self.generate_say_command("All players are ready"),
+            "mp_warmup_pausetimer 0".to_string(),
+            "mp_warmup_end".to_string(),
+        ];
+        if !self.rcon_batch(cmd_vec).await {
+            self.send_match_status_to_main_thread(fallback_status);
+            return;
+        }
+
+        self.say_countdown("Going live in").await;
+        for restart_delay in LIVE_RESTART_SEQUENCE {
+            if !self.rcon_batch(vec![format!("mp_restartgame {}", restart_delay)]).await {
+                self.send_match_status_to_main_thread(fallback_status);
+                return;
             }
-            &_ => {}
+            tokio::time::sleep(Duration::from_secs(restart_delay as u64 + 1)).await;
         }
+
+        let live_say_command = self.generate_say_command("LIVE! GL HF!");
+        self.rcon_batch(vec![live_say_command.clone(), live_say_command.clone(), live_say_command]).await;
+        self.send_match_status_to_main_thread(CsctrlMatchStatus::Live);
     }
 
-    pub async fn rcon(&mut self, command: String) -> bool {
+    async fn say_countdown(&mut self, prefix: &str) {
+        for second in (1..=MATCH_START_COUNTDOWN_SECS).rev() {
+            let say_command = self.generate_say_command(&format!("{} {}...", prefix, second));
+            self.rcon_batch(vec![say_command]).await;
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    fn get_match_cfg_commands(&self, cfg_filename: &str) -> Result<Vec<String>, String> {
+        let mut match_cfg_path = get_csctrl_config_file_path();
+        match_cfg_path.pop();
+        match_cfg_path.push(format!("cfg/{}.cfg", cfg_filename));
+
+        let match_cfg_string = match std::fs::read_to_string(match_cfg_path) {
+            Ok(cfg_string) => { cfg_string }
+            Err(_) => { return Err(format!("Error reading match cfg file '{}'", cfg_filename)); }
+        };
+
+        let fixed_line_endings_split_cfg = match_cfg_string.replace("\r\n", "\n");
+        return Ok(fixed_line_endings_split_cfg.split("\n").map(|cfg_line| cfg_line.to_string()).collect());
+    }
+
+    /// Points the server's http logs to csctrl. Retried while the server can't be reached
+    async fn register_log_address(&mut self) {
+        let cs_log_url = match &self.cs_log_url {
+            Some(cs_log_url) => { cs_log_url.clone() }
+            None => {
+                tracing::error!("Can't register the log address. No address the server can reach csctrl on is configured");
+                return;
+            }
+        };
+
+        self.last_log_address_attempt = Some(Instant::now());
+        let cmd_vec = vec![
+            "log on".to_string(),
+            "mp_logdetail 3".to_string(),
+            "logaddress_delall_http".to_string(),
+            format!("logaddress_add_http \"{}\"", cs_log_url),
+        ];
+
+        if !self.rcon_batch(cmd_vec).await {
+            tracing::warn!("Can't register the log address. Retrying in {} seconds", LOG_ADDRESS_RETRY_SECS);
+            return;
+        }
+
+        tracing::info!("Registered log address");
+        self.is_log_address_registered = true;
+        self.log_address_connection_count = self.rcon_connection.get_connection_count();
+    }
+
+    async fn rcon_batch(&mut self, commands: Vec<String>) -> bool {
+        for command in commands {
+            if self.rcon_with_response(command).await.is_err() {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    pub async fn rcon_with_response(&mut self, command: String) -> Result<String, String> {
         let mut response = match self.rcon_connection.execute_command(&command).await {
             Ok(res) => { res }
             Err(error) => {
+                let error = error.to_string();
                 tracing::error!("Error while attempting rcon command. {}", error);
                 self.last_rcon_success = false;
-                return false;
+                // The server may have restarted and lost its log address
+                self.is_log_address_registered = false;
+                return Err(error);
             }
         };
 
@@ -128,14 +397,51 @@ impl CsctrlServer {
         tracing::trace!("Rcon response:\n{}", response);
 
         self.last_rcon_success = true;
-        return true;
+        return Ok(response);
+    }
+
+    /// Probes the server with 'status' and reports the round trip and its output to the main thread, which decides when it is offline
+    async fn check_health(&mut self) {
+        self.last_health_check = Some(Instant::now());
+
+        let start_time = Instant::now();
+        match self.rcon_connection.execute_command("status").await {
+            Ok(status) => {
+                let latency_ms = start_time.elapsed().as_millis();
+                self.check_status_map(&status);
+                self.send_reply_to_main_thread(CsctrlServerReply::HealthCheckSucceeded { latency_ms: latency_ms as u64, status });
+            }
+            Err(error) => {
+                tracing::debug!("Health check failed. {}", error);
+                // The server may have restarted and lost its log address
+                self.is_log_address_registered = false;
+                self.send_reply_to_main_thread(CsctrlServerReply::HealthCheckFailed);
+            }
+        }
+    }
+
+    /// A map change reloads the server's logging, so the log address is registered again when 'status' reports a different map
+    fn check_status_map(&mut self, status: &str) {
+        let map = parse_server_status(status).map;
+        if map.is_empty() { return; }
+
+        if self.last_status_map.as_ref().is_some_and(|last_status_map| *last_status_map != map) {
+            tracing::info!("Server changed map to '{}'. Registering the log address again", map);
+            self.is_log_address_registered = false;
+        }
+        self.last_status_map = Some(map);
     }
 
     fn generate_say_command(&self, say_text: &str) -> String {
         return format!("say [{}] {}", &get_static_data().read().unwrap().chat_signature, say_text);
     }
 
-    fn send_message_to_main_thread(&self, message: &str) {
-        self.thread_sender.send(format!("{}{}{}", self.address, FORMAT_SEPARATOR, message)).expect("Can't send message to main thread");
+    fn send_match_status_to_main_thread(&self, match_status: CsctrlMatchStatus) {
+        self.send_reply_to_main_thread(CsctrlServerReply::MatchStatus(match_status));
+    }
+
+    fn send_reply_to_main_thread(&self, reply: CsctrlServerReply) {
+        let message = CsctrlMessage::Server { server_address: self.address.clone(), reply };
+        self.thread_sender.send(message).expect("Can't send message to main thread");
     }
 }
\ No newline at end of file
@@ -0,0 +1,199 @@
+use std::str::FromStr;
+use crate::csctrl::steam_id::SteamId;
+use crate::csctrl::types::{CsctrlDataServerInfo, CsctrlDataStatusPlayer};
+
+/// Parses the output of the 'status' rcon command. CS2 doesn't list steam ids in it, so they're
+/// only filled in when the row has one and are otherwise resolved later from the logged connections
+pub fn parse_server_status(status: &str) -> CsctrlDataServerInfo {
+    let mut server_info = CsctrlDataServerInfo::default();
+    let mut is_in_player_list = false;
+
+    for line in status.lines() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+
+        if line.starts_with("---------players") {
+            is_in_player_list = true;
+            continue;
+        }
+        if line.starts_with("#end") {
+            is_in_player_list = false;
+            continue;
+        }
+
+        if is_in_player_list {
+            if let Some(player) = parse_player_row(line) {
+                server_info.players.push(player);
+            }
+            continue;
+        }
+
+        if line.starts_with("loaded spawngroup") && line.contains("main lump") {
+            if let Some(map) = parse_spawngroup_map(line) {
+                server_info.map = map;
+            }
+            continue;
+        }
+
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => { (key.trim(), value.trim()) }
+            None => { continue; }
+        };
+        match key {
+            "hostname" => { server_info.hostname = value.to_string(); }
+            "version" => { server_info.version = value.split_whitespace().next().unwrap_or("").to_string(); }
+            "map" => { server_info.map = value.split_whitespace().next().unwrap_or("").to_string(); }
+            "players" => { parse_player_counts(&mut server_info, value); }
+            _ => {}
+        }
+    }
+
+    return server_info;
+}
+
+/// e.g. 'loaded spawngroup(  1)  : SV:  [1: de_mirage | main lump | mapload]'
+fn parse_spawngroup_map(line: &str) -> Option<String> {
+    let spawngroup = &line[line.find('[')? + 1..];
+    let lump_name = spawngroup.split('|').next()?;
+    let (_, map) = lump_name.split_once(':')?;
+    let map = map.trim();
+    if map.is_empty() { return None; }
+    return Some(map.to_string());
+}
+
+/// e.g. '2 humans, 3 bots (10 max) (not hibernating) (unreserved)'
+fn parse_player_counts(server_info: &mut CsctrlDataServerInfo, value: &str) {
+    let words: Vec<&str> = value.split(|char: char| char.is_whitespace() || char == ',' || char == '(' || char == ')')
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    for index in 1..words.len() {
+        let amount = match words[index - 1].parse::<u16>() {
+            Ok(amount) => { amount }
+            Err(_) => { continue; }
+        };
+        if words[index].starts_with("human") { server_info.human_players = amount; }
+        else if words[index].starts_with("bot") { server_info.bots = amount; }
+        else if words[index] == "max" { server_info.max_players = amount; }
+    }
+}
+
+/// e.g. '2    00:42   12    0     active 786432 203.0.113.7:27005 'Name'' or '3      BOT    0    0     active      0 'Name''.
+/// Rows of players still connecting report the id 65535 and are skipped
+fn parse_player_row(line: &str) -> Option<CsctrlDataStatusPlayer> {
+    let name_start = line.find('\'')?;
+    let name_end = line.rfind('\'')?;
+    if name_end <= name_start { return None; }
+
+    let name = line[name_start + 1..name_end].to_string();
+    let columns: Vec<&str> = line[..name_start].split_whitespace().collect();
+    if columns.len() < 5 { return None; }
+
+    let user_id = columns[0].parse::<u16>().ok()?;
+    if user_id == u16::MAX { return None; }
+
+    let is_bot = columns[1] == "BOT";
+    let steam_id = columns.iter().find_map(|column| {
+        if !column.starts_with("[U:") && !column.starts_with("STEAM_") { return None; }
+        return SteamId::from_str(column).ok();
+    });
+
+    return Some(CsctrlDataStatusPlayer {
+        user_id,
+        name,
+        steam_id: if is_bot { Some(SteamId::Bot) } else { steam_id },
+        connected_secs: parse_connected_time(columns[1]),
+        ping: columns[2].parse::<u16>().unwrap_or(0),
+        loss: columns[3].parse::<u16>().unwrap_or(0),
+        state: columns[4].to_string(),
+        is_bot,
+    });
+}
+
+/// Connected time is printed as 'mm:ss' or 'hh:mm:ss'
+fn parse_connected_time(time: &str) -> Option<u64> {
+    let mut connected_secs = 0;
+    for time_part in time.split(':') {
+        connected_secs = connected_secs * 60 + time_part.parse::<u64>().ok()?;
+    }
+    return Some(connected_secs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CS2_STATUS: &str = "Server:  Running [0.0.0.0:27015]
+Client:  Disconnected
+@ Current  :  game
+source   : console
+hostname : csctrl | Match Server
+spawn    : 1
+version  : 1.40.1.9/14019 14019 secure  public
+steamid  : [A:1:1234567890:12345] (90212345678901234)
+udp/ip   : 0.0.0.0:27015 (public 203.0.113.10:27015)
+os/type  : Linux dedicated
+players  : 2 humans, 1 bots (12 max) (not hibernating) (unreserved)
+loaded spawngroup(  1)  : SV:  [1: de_ancient | main lump | mapload]
+---------players--------
+  id     time ping loss      state   rate adr name
+65535 [NoChan]    0    0 challenging      0unknown ''
+    2    12:34   18    0     active 786432 203.0.113.7:27005 'O'Neil'
+    3 01:02:03   35    1     active 786432 198.51.100.4:27005 'fallen'
+    4      BOT    0    0     active      0 'Bot Rex'
+#end
+";
+
+    #[test]
+    fn parses_server_details() {
+        let server_info = parse_server_status(CS2_STATUS);
+        assert_eq!(server_info.hostname, "csctrl | Match Server");
+        assert_eq!(server_info.version, "1.40.1.9/14019");
+        assert_eq!(server_info.map, "de_ancient");
+        assert_eq!(server_info.human_players, 2);
+        assert_eq!(server_info.bots, 1);
+        assert_eq!(server_info.max_players, 12);
+    }
+
+    #[test]
+    fn parses_player_rows_and_skips_connecting_players() {
+        let server_info = parse_server_status(CS2_STATUS);
+        let user_ids: Vec<u16> = server_info.players.iter().map(|player| player.user_id).collect();
+        assert_eq!(user_ids, vec![2, 3, 4]);
+
+        let player = &server_info.players[0];
+        assert_eq!(player.name, "O'Neil");
+        assert_eq!(player.connected_secs, Some(754));
+        assert_eq!(player.ping, 18);
+        assert_eq!(player.loss, 0);
+        assert_eq!(player.state, "active");
+        assert_eq!(player.steam_id, None);
+        assert!(!player.is_bot);
+
+        assert_eq!(server_info.players[1].connected_secs, Some(3723));
+        assert_eq!(server_info.players[1].loss, 1);
+    }
+
+    #[test]
+    fn parses_bot_rows() {
+        let server_info = parse_server_status(CS2_STATUS);
+        let bot = &server_info.players[2];
+        assert_eq!(bot.name, "Bot Rex");
+        assert!(bot.is_bot);
+        assert_eq!(bot.steam_id, Some(SteamId::Bot));
+        assert_eq!(bot.connected_secs, None);
+    }
+
+    #[test]
+    fn parses_steam_ids_when_listed() {
+        let server_info = parse_server_status("---------players--------\n    2    00:42   12    0     active 786432 [U:1:102286709] 203.0.113.7:27005 'Name'\n#end");
+        assert_eq!(server_info.players[0].steam_id, Some(SteamId::from_str("76561198062552437").unwrap()));
+    }
+
+    #[test]
+    fn falls_back_to_the_map_line() {
+        let server_info = parse_server_status("hostname : csctrl\nmap      : de_nuke\nplayers  : 0 humans, 0 bots (10 max)");
+        assert_eq!(server_info.map, "de_nuke");
+        assert!(server_info.players.is_empty());
+    }
+}
\ No newline at end of file
@@ -0,0 +1,229 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// SteamID64 of the account with id 0 in the public universe
+const STEAM_ID_64_BASE: u64 = 76561197960265728;
+
+/// A steam account identified by its account id so SteamID64, SteamID2 and SteamID3 forms compare equal.
+/// Bots don't have an account and are all reported as 'BOT' by the server
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SteamId {
+    Bot,
+    Account(u32),
+}
+
+impl SteamId {
+    pub fn is_bot(&self) -> bool {
+        return *self == SteamId::Bot;
+    }
+
+    pub fn to_steam_id_64(self) -> Option<u64> {
+        return match self {
+            SteamId::Bot => { None }
+            SteamId::Account(account_id) => { Some(STEAM_ID_64_BASE + account_id as u64) }
+        };
+    }
+
+    pub fn to_steam_id_2(self) -> String {
+        return match self {
+            SteamId::Bot => { "BOT".to_string() }
+            SteamId::Account(account_id) => { format!("STEAM_1:{}:{}", account_id % 2, account_id / 2) }
+        };
+    }
+
+    pub fn to_steam_id_3(self) -> String {
+        return match self {
+            SteamId::Bot => { "BOT".to_string() }
+            SteamId::Account(account_id) => { format!("[U:1:{}]", account_id) }
+        };
+    }
+
+    fn parse_steam_id_2(steam_id: &str) -> Result<SteamId, String> {
+        let split_steam_id: Vec<&str> = steam_id[6..].split(':').collect();
+        // The universe is 0 in older games and 1 in newer ones, both meaning the public universe
+        if split_steam_id.len() != 3 || (split_steam_id[0] != "0" && split_steam_id[0] != "1") {
+            return Err(format!("'{}' is not a valid SteamID2", steam_id));
+        }
+
+        let y = split_steam_id[1].parse::<u32>();
+        let z = split_steam_id[2].parse::<u32>();
+        return match (y, z) {
+            (Ok(y), Ok(z)) if y <= 1 && z <= u32::MAX / 2 => { Ok(SteamId::Account(z * 2 + y)) }
+            _ => { Err(format!("'{}' is not a valid SteamID2", steam_id)) }
+        };
+    }
+
+    fn parse_steam_id_3(steam_id: &str) -> Result<SteamId, String> {
+        let split_steam_id: Vec<&str> = steam_id.split(':').collect();
+        if split_steam_id.len() != 3 || split_steam_id[0] != "U" || split_steam_id[1] != "1" {
+            return Err(format!("'{}' is not a valid individual SteamID3", steam_id));
+        }
+
+        return match split_steam_id[2].parse::<u32>() {
+            Ok(account_id) => { Ok(SteamId::Account(account_id)) }
+            Err(_) => { Err(format!("'{}' is not a valid individual SteamID3", steam_id)) }
+        };
+    }
+
+    fn parse_steam_id_64(steam_id: &str) -> Result<SteamId, String> {
+        let steam_id_64 = match steam_id.parse::<u64>() {
+            Ok(steam_id_64) => { steam_id_64 }
+            Err(_) => { return Err(format!("'{}' is not a valid SteamID64", steam_id)); }
+        };
+
+        if steam_id_64 < STEAM_ID_64_BASE || steam_id_64 - STEAM_ID_64_BASE > u32::MAX as u64 {
+            return Err(format!("'{}' is not a valid individual SteamID64", steam_id));
+        }
+        return Ok(SteamId::Account((steam_id_64 - STEAM_ID_64_BASE) as u32));
+    }
+}
+
+impl FromStr for SteamId {
+    type Err = String;
+
+    fn from_str(steam_id: &str) -> Result<Self, Self::Err> {
+        let steam_id = steam_id.trim();
+        if steam_id.eq_ignore_ascii_case("BOT") {
+            return Ok(SteamId::Bot);
+        }
+
+        if steam_id.to_ascii_uppercase().starts_with("STEAM_") {
+            return SteamId::parse_steam_id_2(steam_id);
+        }
+
+        // Logs report SteamID3 without the brackets while admins usually paste them with
+        let trimmed_steam_id = steam_id.trim_matches(|char| char == '[' || char == ']');
+        if trimmed_steam_id.contains(':') {
+            return SteamId::parse_steam_id_3(trimmed_steam_id);
+        }
+
+        return SteamId::parse_steam_id_64(steam_id);
+    }
+}
+
+impl TryFrom<String> for SteamId {
+    type Error = String;
+
+    fn try_from(steam_id: String) -> Result<Self, Self::Error> {
+        return SteamId::from_str(&steam_id);
+    }
+}
+
+impl From<SteamId> for String {
+    fn from(steam_id: SteamId) -> Self {
+        return steam_id.to_steam_id_3();
+    }
+}
+
+impl fmt::Display for SteamId {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(formatter, "{}", self.to_steam_id_3());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT_ID: u32 = 102286709;
+
+    #[test]
+    fn parses_steam_id_64() {
+        assert_eq!(SteamId::from_str("76561198062552437"), Ok(SteamId::Account(ACCOUNT_ID)));
+        assert_eq!(SteamId::from_str(" 76561198062552437 "), Ok(SteamId::Account(ACCOUNT_ID)));
+        assert_eq!(SteamId::from_str("76561197960265728"), Ok(SteamId::Account(0)));
+    }
+
+    #[test]
+    fn parses_steam_id_2() {
+        assert_eq!(SteamId::from_str("STEAM_1:1:51143354"), Ok(SteamId::Account(ACCOUNT_ID)));
+        assert_eq!(SteamId::from_str("STEAM_0:1:51143354"), Ok(SteamId::Account(ACCOUNT_ID)));
+        assert_eq!(SteamId::from_str("steam_1:0:51143354"), Ok(SteamId::Account(ACCOUNT_ID - 1)));
+    }
+
+    #[test]
+    fn parses_steam_id_3_with_and_without_brackets() {
+        assert_eq!(SteamId::from_str("[U:1:102286709]"), Ok(SteamId::Account(ACCOUNT_ID)));
+        assert_eq!(SteamId::from_str("U:1:102286709"), Ok(SteamId::Account(ACCOUNT_ID)));
+    }
+
+    #[test]
+    fn parses_bots() {
+        assert_eq!(SteamId::from_str("BOT"), Ok(SteamId::Bot));
+        assert_eq!(SteamId::from_str("bot"), Ok(SteamId::Bot));
+        assert!(SteamId::Bot.is_bot());
+        assert!(!SteamId::Account(ACCOUNT_ID).is_bot());
+    }
+
+    #[test]
+    fn converts_between_formats() {
+        let steam_id = SteamId::Account(ACCOUNT_ID);
+        assert_eq!(steam_id.to_steam_id_64(), Some(76561198062552437));
+        assert_eq!(steam_id.to_steam_id_2(), "STEAM_1:1:51143354");
+        assert_eq!(steam_id.to_steam_id_3(), "[U:1:102286709]");
+        assert_eq!(steam_id.to_string(), "[U:1:102286709]");
+
+        assert_eq!(SteamId::Bot.to_steam_id_64(), None);
+        assert_eq!(SteamId::Bot.to_steam_id_2(), "BOT");
+        assert_eq!(SteamId::Bot.to_steam_id_3(), "BOT");
+    }
+
+    #[test]
+    fn every_format_of_an_account_compares_equal() {
+        let steam_id_64 = SteamId::from_str("76561198062552437").unwrap();
+        let steam_id_2 = SteamId::from_str("STEAM_1:1:51143354").unwrap();
+        let steam_id_3 = SteamId::from_str("[U:1:102286709]").unwrap();
+        assert_eq!(steam_id_64, steam_id_2);
+        assert_eq!(steam_id_2, steam_id_3);
+    }
+
+    #[test]
+    fn rejects_invalid_steam_ids() {
+        let invalid_steam_ids = [
+            "",
+            "not a steam id",
+            "STEAM_1:1",
+            "STEAM_x:1:5",
+            "STEAM_2:1:51143354",
+            "STEAM_:1:51143354",
+            "STEAM_1:2:51143354",
+            "STEAM_1:1:abc",
+            "STEAM_1:1:4294967295",
+            "[U:1:abc]",
+            "[G:1:102286709]",
+            "[U:0:102286709]",
+            "U:1:102286709:1",
+            "76561197960265727",
+            "80857165254233024",
+            "18446744073709551616",
+        ];
+        for invalid_steam_id in invalid_steam_ids {
+            assert!(SteamId::from_str(invalid_steam_id).is_err(), "'{}' should not parse", invalid_steam_id);
+        }
+    }
+
+    #[test]
+    fn serializes_as_steam_id_3() {
+        assert_eq!(serde_json::to_string(&SteamId::Account(ACCOUNT_ID)).unwrap(), "\"[U:1:102286709]\"");
+        assert_eq!(serde_json::to_string(&SteamId::Bot).unwrap(), "\"BOT\"");
+    }
+
+    #[test]
+    fn deserializes_every_format_and_round_trips() {
+        for steam_id in ["\"76561198062552437\"", "\"STEAM_1:1:51143354\"", "\"[U:1:102286709]\""] {
+            assert_eq!(serde_json::from_str::<SteamId>(steam_id).unwrap(), SteamId::Account(ACCOUNT_ID));
+        }
+
+        for steam_id in [SteamId::Account(ACCOUNT_ID), SteamId::Account(0), SteamId::Bot] {
+            let serialized_steam_id = serde_json::to_string(&steam_id).unwrap();
+            assert_eq!(serde_json::from_str::<SteamId>(&serialized_steam_id).unwrap(), steam_id);
+        }
+    }
+
+    #[test]
+    fn fails_to_deserialize_invalid_steam_ids() {
+        assert!(serde_json::from_str::<SteamId>("\"STEAM_1:2:1\"").is_err());
+        assert!(serde_json::from_str::<SteamId>("76561198062552437").is_err());
+    }
+}
\ No newline at end of file
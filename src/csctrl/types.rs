@@ -1,14 +1,41 @@
 use std::collections::HashMap;
+use crate::csctrl::steam_id::SteamId;
 use crate::system::utilities::get_csctrl_config_file_path;
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct CsctrlConfig {
+    /// Bearer token of the rest api. Kept apart from the secret, which every CS2 server learns through its log url
+    #[serde(default)]
+    pub api_token: String,
     pub chat_signature: String,
+    /// Address the CS2 servers use to reach csctrl, e.g. 'http://203.0.113.7:27016'. Falls back to the rest api address
+    #[serde(default)]
+    pub cs_log_address: String,
     pub cs_listen_path: String,
     pub rest_api_address: String,
     pub secret: String,
     pub servers: Vec<CsctrlServerSetup>,
     pub tracing_env_filter: String,
+    /// Urls notified of the match lifecycle of every server
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+}
+
+impl CsctrlConfig {
+    /// Full url the CS2 servers post their logs to, secret included. None when the servers have no address to reach csctrl on
+    pub fn get_cs_log_url(&self) -> Option<String> {
+        let cs_log_address = if self.cs_log_address.is_empty() {
+            let is_wildcard_address = self.rest_api_address.parse::<std::net::SocketAddr>().is_ok_and(|address| address.ip().is_unspecified());
+            if is_wildcard_address {
+                tracing::error!("The rest api listens on the wildcard address '{}', which the servers can't reach. Set cs_log_address to register the log address", self.rest_api_address);
+                return None;
+            }
+            format!("http://{}", self.rest_api_address)
+        } else {
+            self.cs_log_address.clone()
+        };
+        return Some(format!("{}{}/{}", cs_log_address.trim_end_matches('/'), self.cs_listen_path.trim_end_matches('/'), self.secret));
+    }
 }
 
 #[derive(Clone)]
@@ -21,7 +48,10 @@ pub struct CsctrlServerSetup {
     pub name: String,
     pub address: String,
     pub rcon_password: String,
-    pub match_setup: String
+    pub match_setup: String,
+    /// Urls notified of the match lifecycle of this server only
+    #[serde(default)]
+    pub webhooks: Vec<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
@@ -31,9 +61,121 @@ pub struct MatchSetup {
     pub knife_round: bool,
     pub cfg_filename: String,
     pub player_amount: i8,
+    #[serde(default = "MatchSetup::default_tactical_timeouts_per_team")]
+    pub tactical_timeouts_per_team: u8,
+    #[serde(default = "MatchSetup::default_tactical_timeout_duration_secs")]
+    pub tactical_timeout_duration_secs: u64,
+    #[serde(default)]
+    pub maps: Vec<MatchSetupMap>,
+    #[serde(default)]
+    pub veto: Option<MatchSetupVeto>,
+    #[serde(default)]
+    pub team_a_players: Vec<SteamId>,
+    #[serde(default)]
+    pub team_a_coaches: Vec<SteamId>,
+    #[serde(default)]
+    pub team_b_players: Vec<SteamId>,
+    #[serde(default)]
+    pub team_b_coaches: Vec<SteamId>,
+}
+
+/// Captains are identified by their steam id. Without a captain any player of that team can veto,
+/// with team A on the CT side and team B on the T side
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct MatchSetupVeto {
+    pub map_pool: Vec<String>,
+    pub steps: Vec<MatchSetupVetoStep>,
+    #[serde(default)]
+    pub team_a_captain: Option<SteamId>,
+    #[serde(default)]
+    pub team_b_captain: Option<SteamId>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct MatchSetupVetoStep {
+    pub team: MatchSetupTeam,
+    pub action: MatchSetupVetoAction,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub enum MatchSetupTeam {
+    TeamA,
+    TeamB,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub enum MatchSetupVetoAction {
+    Ban,
+    Pick,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct MatchSetupMap {
+    pub name: String,
+    pub side: MatchSetupMapSide,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub enum MatchSetupMapSide {
+    Knife,
+    TeamAStartsCt,
+    TeamAStartsT,
+}
+
+impl Default for MatchSetup {
+    fn default() -> Self {
+        MatchSetup {
+            team_a_name: "".to_string(),
+            team_b_name: "".to_string(),
+            knife_round: false,
+            cfg_filename: "".to_string(),
+            player_amount: 0,
+            tactical_timeouts_per_team: MatchSetup::default_tactical_timeouts_per_team(),
+            tactical_timeout_duration_secs: MatchSetup::default_tactical_timeout_duration_secs(),
+            maps: vec![],
+            veto: None,
+            team_a_players: vec![],
+            team_a_coaches: vec![],
+            team_b_players: vec![],
+            team_b_coaches: vec![],
+        }
+    }
 }
 
 impl MatchSetup {
+    pub fn get_team_name(&self, team: &MatchSetupTeam) -> String {
+        return match team {
+            MatchSetupTeam::TeamA => { self.team_a_name.clone() }
+            MatchSetupTeam::TeamB => { self.team_b_name.clone() }
+        };
+    }
+
+    /// A team that doesn't list any players is open to everyone
+    pub fn has_roster(&self, team: &MatchSetupTeam) -> bool {
+        return match team {
+            MatchSetupTeam::TeamA => { !self.team_a_players.is_empty() }
+            MatchSetupTeam::TeamB => { !self.team_b_players.is_empty() }
+        };
+    }
+
+    /// Players on no roster may still play for a team that doesn't list its players, so they're only turned away when both do
+    pub fn has_every_roster(&self) -> bool {
+        return self.has_roster(&MatchSetupTeam::TeamA) && self.has_roster(&MatchSetupTeam::TeamB);
+    }
+
+    /// Returns the team a steam id is registered on and whether it is registered as a coach
+    pub fn get_roster_team(&self, steam_id: &SteamId) -> Option<(MatchSetupTeam, bool)> {
+        let is_in_list = |list: &Vec<SteamId>| list.contains(steam_id);
+        if is_in_list(&self.team_a_players) { return Some((MatchSetupTeam::TeamA, false)); }
+        if is_in_list(&self.team_b_players) { return Some((MatchSetupTeam::TeamB, false)); }
+        if is_in_list(&self.team_a_coaches) { return Some((MatchSetupTeam::TeamA, true)); }
+        if is_in_list(&self.team_b_coaches) { return Some((MatchSetupTeam::TeamB, true)); }
+        return None;
+    }
+
+    fn default_tactical_timeouts_per_team() -> u8 { 3 }
+    fn default_tactical_timeout_duration_secs() -> u64 { 30 }
+
     pub fn load_match_setup(file_name: &str) -> Result<MatchSetup, String> {
         let match_setup_string = match MatchSetup::load_match_setup_as_string(file_name) {
             Ok(valid_string) => { valid_string }
@@ -60,67 +202,464 @@ impl MatchSetup {
     }
 }
 
+/// Events pushed to the rest api event stream as server logs are parsed
+#[derive(Clone, serde::Serialize)]
+#[serde(tag = "type")]
+pub enum CsctrlEvent {
+    /// Full server state, sent when a client connects so it doesn't have to wait for the next change
+    Snapshot { server: Box<CsctrlDataServer> },
+    StatusChange { status: CsctrlMatchStatus, previous_status: Option<CsctrlMatchStatus> },
+    ScoreChange { team_ct_name: String, team_ct_score: u8, team_t_name: String, team_t_score: u8 },
+    RoundEnd { round: u8, winner_side: String, reason: String },
+    Halftime { team_ct_name: String, team_ct_score: u8, team_t_name: String, team_t_score: u8 },
+    MatchFinished { result: CsctrlMatchResult },
+    Kill { attacker_name: String, attacker_steam_id: SteamId, victim_name: String, victim_steam_id: SteamId, weapon: String, is_headshot: bool },
+    ReadyChange { player_name: String, steam_id: SteamId, is_ready: bool, player_ready_amount: i8 },
+    Chat { player_name: String, steam_id: SteamId, team_side: String, message: String },
+}
+
+impl CsctrlEvent {
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            CsctrlEvent::Snapshot { .. } => { "Snapshot" }
+            CsctrlEvent::StatusChange { .. } => { "StatusChange" }
+            CsctrlEvent::ScoreChange { .. } => { "ScoreChange" }
+            CsctrlEvent::RoundEnd { .. } => { "RoundEnd" }
+            CsctrlEvent::Halftime { .. } => { "Halftime" }
+            CsctrlEvent::MatchFinished { .. } => { "MatchFinished" }
+            CsctrlEvent::Kill { .. } => { "Kill" }
+            CsctrlEvent::ReadyChange { .. } => { "ReadyChange" }
+            CsctrlEvent::Chat { .. } => { "Chat" }
+        };
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlServerEvent {
+    pub server_address: String,
+    #[serde(flatten)]
+    pub event: CsctrlEvent,
+}
+
+/// Everything the main thread handles arrives through a single channel of these
+pub enum CsctrlMessage {
+    /// A command typed in the terminal or issued through the rest api. Only the rest api waits for the result
+    Command { target_address: String, command: String, responder: Option<tokio::sync::oneshot::Sender<crate::commands::base::CommandResult>> },
+    /// Body of a log request posted by a CS2 server, possibly holding several lines
+    Weblog { server_address: String, body: String },
+    Server { server_address: String, reply: CsctrlServerReply },
+}
+
+/// Work the main thread hands to a server thread
+#[derive(Debug)]
+pub enum CsctrlServerRequest {
+    Rcon { request_id: u64, command: String },
+    RegisterLogAddress,
+    MatchStart,
+    MatchKnife,
+    MatchLive,
+    MatchKnifeEnd { winner_team_name: String },
+    MatchStay,
+    MatchSwitch,
+    MatchPause { announcement: String },
+    MatchUnpause { announcement: String },
+    MatchEnd { announcement: String },
+    ChangeLevel { map: String },
+    SeriesNext { map: String, announcement: String },
+    Kick { user_id: u16, reason: String },
+    Say { message: String },
+}
+
+/// What a server thread reports back once it acted on the server
+pub enum CsctrlServerReply {
+    MatchStatus(CsctrlMatchStatus),
+    RconResult { request_id: u64, result: crate::commands::base::CommandResult },
+    HealthCheckSucceeded { latency_ms: u64, status: String },
+    HealthCheckFailed,
+}
+
+/// An rcon command waiting for its server thread to answer. Commands typed in the terminal have no responder
+pub struct CsctrlRconRequest {
+    pub target_address: String,
+    pub command: String,
+    pub responder: Option<tokio::sync::oneshot::Sender<crate::commands::base::CommandResult>>,
+    pub registered_at: std::time::Instant,
+}
+
 pub struct CsctrlServerContainer {
-    pub thread: std::thread::JoinHandle<()>,
-    pub sender: tokio::sync::mpsc::UnboundedSender<String>
+    pub task: tokio::task::JoinHandle<()>,
+    pub sender: tokio::sync::mpsc::UnboundedSender<CsctrlServerRequest>
 }
 
-#[derive(Clone)]
+/// Keeps the rcon password out of anything served by the rest api
+fn serialize_server_setup_without_password<S: serde::Serializer>(setup: &CsctrlServerSetup, serializer: S) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeStruct;
+    let mut serialized_setup = serializer.serialize_struct("CsctrlServerSetup", 3)?;
+    serialized_setup.serialize_field("name", &setup.name)?;
+    serialized_setup.serialize_field("address", &setup.address)?;
+    serialized_setup.serialize_field("match_setup", &setup.match_setup)?;
+    return serialized_setup.end();
+}
+
+#[derive(Clone, serde::Serialize)]
 pub struct CsctrlDataParent {
     pub servers: HashMap<String, CsctrlDataServer>
 }
 
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize)]
 pub struct CsctrlDataServer {
+    #[serde(serialize_with = "serialize_server_setup_without_password")]
     pub config: CsctrlServerSetup,
     pub is_online: bool,
+    pub health: CsctrlDataHealth,
+    pub server_info: CsctrlDataServerInfo,
     pub team_ct: CsctrlDataTeam,
     pub team_t: CsctrlDataTeam,
     pub status: CsctrlMatchStatus,
     pub player_ready_amount: i8,
+    pub knife_winner_side: String,
+    pub map: String,
+    pub rounds_played: u8,
+    pub last_round_winner_side: String,
+    pub last_round_reason: String,
+    pub max_rounds: u8,
+    pub overtime_max_rounds: u8,
+    pub is_side_swap_pending: bool,
+    pub is_freeze_period: bool,
+    pub player_stats: HashMap<String, CsctrlDataPlayerStats>,
+    pub connections: HashMap<String, CsctrlDataConnection>,
+    pub pauses: Vec<CsctrlDataPause>,
+    pub match_started_at: u64,
+    pub series: CsctrlDataSeries,
+    pub veto: CsctrlDataVeto,
+    #[serde(skip)]
     pub logs: Vec<String>,
     pub match_setup: MatchSetup
 }
 
-#[derive(Clone)]
+impl CsctrlDataServer {
+    pub fn new(config: CsctrlServerSetup, match_setup: MatchSetup) -> CsctrlDataServer {
+        return CsctrlDataServer {
+            config,
+            is_online: false,
+            health: Default::default(),
+            server_info: Default::default(),
+            team_ct: CsctrlDataTeam {
+                setup_team: MatchSetupTeam::TeamA,
+                name: "".to_string(),
+                score: 0,
+                players: vec![],
+            },
+            team_t: CsctrlDataTeam {
+                setup_team: MatchSetupTeam::TeamB,
+                name: "".to_string(),
+                score: 0,
+                players: vec![],
+            },
+            status: CsctrlMatchStatus::NoHook,
+            player_ready_amount: 0,
+            knife_winner_side: "".to_string(),
+            map: "".to_string(),
+            rounds_played: 0,
+            last_round_winner_side: "".to_string(),
+            last_round_reason: "".to_string(),
+            max_rounds: 24,
+            overtime_max_rounds: 6,
+            is_side_swap_pending: false,
+            is_freeze_period: false,
+            player_stats: HashMap::new(),
+            connections: HashMap::new(),
+            pauses: vec![],
+            match_started_at: 0,
+            series: Default::default(),
+            veto: Default::default(),
+            logs: vec![],
+            match_setup,
+        };
+    }
+
+    pub fn is_halftime(&self) -> bool {
+        let rounds_played = self.rounds_played as u16;
+        let max_rounds = self.max_rounds as u16;
+        let overtime_max_rounds = self.overtime_max_rounds as u16;
+
+        if rounds_played == max_rounds / 2 { return true; }
+        if rounds_played <= max_rounds || overtime_max_rounds < 2 { return false; }
+
+        // Overtime halves keep the side the previous half ended on and only swap midway through
+        return (rounds_played - max_rounds) % overtime_max_rounds == overtime_max_rounds / 2;
+    }
+
+    pub fn get_current_series_map(&self) -> Option<&MatchSetupMap> {
+        if !self.series.is_active { return None; }
+        return self.series.maps.get(self.series.map_index);
+    }
+
+    pub fn uses_knife_round(&self) -> bool {
+        return match self.get_current_series_map() {
+            Some(series_map) => { series_map.side == MatchSetupMapSide::Knife }
+            None => { self.match_setup.knife_round }
+        };
+    }
+
+    /// Returns the teams starting on the CT and T sides respectively
+    pub fn get_starting_teams(&self) -> (MatchSetupTeam, MatchSetupTeam) {
+        return match self.get_current_series_map() {
+            Some(series_map) if series_map.side == MatchSetupMapSide::TeamAStartsT => { (MatchSetupTeam::TeamB, MatchSetupTeam::TeamA) }
+            _ => { (MatchSetupTeam::TeamA, MatchSetupTeam::TeamB) }
+        };
+    }
+
+    /// Returns the names of the teams starting on the CT and T sides respectively
+    pub fn get_starting_team_names(&self) -> (String, String) {
+        let (team_ct, team_t) = self.get_starting_teams();
+        return (self.match_setup.get_team_name(&team_ct), self.match_setup.get_team_name(&team_t));
+    }
+
+    /// Team names can be empty, so the match setup team is what follows a team through side swaps
+    pub fn get_setup_team_for_side(&self, side: &str) -> MatchSetupTeam {
+        if side.eq_ignore_ascii_case("CT") { return self.team_ct.setup_team.clone(); }
+        return self.team_t.setup_team.clone();
+    }
+
+    pub fn get_active_pause(&mut self) -> Option<&mut CsctrlDataPause> {
+        return self.pauses.iter_mut().filter(|pause| pause.ended_at.is_none()).last();
+    }
+
+    pub fn get_tactical_timeouts_used(&self, team: &MatchSetupTeam) -> u8 {
+        return self.pauses.iter().filter(|pause| pause.pause_type == CsctrlPauseType::Tactical && pause.team == *team).count() as u8;
+    }
+}
+
+/// Outcome of the periodic rcon probes sent by the server thread
+#[derive(Clone, Default, serde::Serialize)]
+pub struct CsctrlDataHealth {
+    pub latency_ms: Option<u64>,
+    pub last_seen_at: Option<u64>,
+    pub last_check_at: Option<u64>,
+    pub consecutive_failures: u32,
+}
+
+/// Server state read from the 'status' output of the last successful health check
+#[derive(Clone, Default, serde::Serialize)]
+pub struct CsctrlDataServerInfo {
+    pub hostname: String,
+    pub map: String,
+    pub version: String,
+    pub human_players: u16,
+    pub bots: u16,
+    pub max_players: u16,
+    pub players: Vec<CsctrlDataStatusPlayer>,
+    pub updated_at: Option<u64>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlDataStatusPlayer {
+    pub user_id: u16,
+    pub name: String,
+    pub steam_id: Option<SteamId>,
+    pub connected_secs: Option<u64>,
+    pub ping: u16,
+    pub loss: u16,
+    pub state: String,
+    pub is_bot: bool,
+}
+
+#[derive(Clone, Default, serde::Serialize)]
+pub struct CsctrlDataSeries {
+    pub is_active: bool,
+    pub maps: Vec<MatchSetupMap>,
+    pub map_index: usize,
+    pub team_a_maps_won: u8,
+    pub team_b_maps_won: u8,
+    pub map_results: Vec<CsctrlSeriesMapResult>,
+}
+
+impl CsctrlDataSeries {
+    pub fn get_maps_to_win(&self) -> u8 {
+        return (self.maps.len() / 2 + 1) as u8;
+    }
+
+    pub fn is_clinched(&self) -> bool {
+        let maps_to_win = self.get_maps_to_win();
+        return self.team_a_maps_won >= maps_to_win || self.team_b_maps_won >= maps_to_win;
+    }
+}
+
+#[derive(Clone, Default, serde::Serialize)]
+pub struct CsctrlDataVeto {
+    pub remaining_maps: Vec<String>,
+    pub actions: Vec<CsctrlVetoAction>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlVetoAction {
+    pub team_name: String,
+    pub action: MatchSetupVetoAction,
+    pub map: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlSeriesMapResult {
+    pub map: String,
+    pub winner: String,
+    pub team_a_score: u8,
+    pub team_b_score: u8,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlDataPause {
+    pub pause_type: CsctrlPauseType,
+    pub team: MatchSetupTeam,
+    pub team_name: String,
+    pub round: u8,
+    /// Pauses only take effect in freeze time, so this stays empty until the next freeze period starts
+    pub started_at: Option<u64>,
+    pub ended_at: Option<u64>,
+    pub unpause_requested_ct: bool,
+    pub unpause_requested_t: bool,
+}
+
+#[derive(Clone, PartialEq, Debug, serde::Serialize)]
+pub enum CsctrlPauseType {
+    Tactical,
+    Technical,
+}
+
+#[derive(Clone, serde::Serialize)]
 pub struct CsctrlDataPlayer {
     pub name: String,
-    pub steam3: String,
+    pub steam_id: SteamId,
     pub is_ready: bool,
 }
 
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlDataConnection {
+    pub name: String,
+    pub steam_id: SteamId,
+    pub user_id: u16,
+    pub state: CsctrlConnectionState,
+    pub address: String,
+    pub disconnect_reason: String,
+}
+
+#[derive(Clone, PartialEq, serde::Serialize)]
+pub enum CsctrlConnectionState {
+    Connected,
+    Validated,
+    InGame,
+    Disconnected,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlDataPlayerStats {
+    #[serde(skip)]
+    pub steam_id: SteamId,
+    pub name: String,
+    pub kills: u16,
+    pub deaths: u16,
+    pub assists: u16,
+    pub headshot_kills: u16,
+    pub damage: u32,
+    /// Health left this round, used to leave overkill out of the damage
+    #[serde(skip)]
+    pub round_health: u8,
+}
+
+impl CsctrlDataPlayerStats {
+    pub fn new(steam_id: SteamId, name: &str) -> CsctrlDataPlayerStats {
+        return CsctrlDataPlayerStats {
+            steam_id,
+            name: name.to_string(),
+            kills: 0,
+            deaths: 0,
+            assists: 0,
+            headshot_kills: 0,
+            damage: 0,
+            round_health: 100,
+        };
+    }
+
+    pub fn average_damage_per_round(&self, rounds_played: u8) -> f32 {
+        if rounds_played == 0 { return 0.0; }
+        return self.damage as f32 / rounds_played as f32;
+    }
+
+    pub fn headshot_percentage(&self) -> f32 {
+        if self.kills == 0 { return 0.0; }
+        return self.headshot_kills as f32 / self.kills as f32 * 100.0;
+    }
+
+    pub fn kill_death_ratio(&self) -> f32 {
+        if self.deaths == 0 { return self.kills as f32; }
+        return self.kills as f32 / self.deaths as f32;
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
 pub struct CsctrlDataTeam {
+    pub setup_team: MatchSetupTeam,
     pub name: String,
     pub score: u8,
     pub players: Vec<CsctrlDataPlayer>
 }
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Debug, serde::Serialize)]
 pub enum CsctrlMatchStatus {
     NoHook,
+    Veto,
     PreMatchWarmup,
+    ChangingMap,
     KnifeRound,
     SwitchTeamsWarmup,
     Live,
     Finished,
     Paused,
-    Invalid
-}
-
-impl CsctrlMatchStatus {
-    pub fn string_to_enum(string: &str) -> CsctrlMatchStatus {
-        return match string {
-            "NoHook" => { CsctrlMatchStatus::NoHook },
-            "PreMatchWarmup" => { CsctrlMatchStatus::PreMatchWarmup },
-            "KnifeRound" => { CsctrlMatchStatus::KnifeRound },
-            "SwitchTeamsWarmup" => { CsctrlMatchStatus::SwitchTeamsWarmup },
-            "Live" => { CsctrlMatchStatus::Live },
-            "Finished" => { CsctrlMatchStatus::Finished },
-            "Paused" => { CsctrlMatchStatus::Paused },
-            &_ => { CsctrlMatchStatus::Invalid }
-        }
-    }
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlMatchResult {
+    pub server_address: String,
+    pub map: String,
+    pub winner: String,
+    pub teams: Vec<CsctrlMatchResultTeam>,
+    pub rounds_played: u8,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub duration_secs: u64,
+    pub players: Vec<CsctrlMatchResultPlayer>,
+    pub pauses: Vec<CsctrlDataPause>,
+    pub veto: Vec<CsctrlVetoAction>,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlMatchResultTeam {
+    pub name: String,
+    pub side: String,
+    pub score: u8,
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct CsctrlMatchResultPlayer {
+    pub steam_id: SteamId,
+    pub steam_id_64: Option<u64>,
+    #[serde(flatten)]
+    pub stats: CsctrlDataPlayerStats,
+    pub average_damage_per_round: f32,
+    pub headshot_percentage: f32,
+    pub kill_death_ratio: f32,
+}
+
+#[derive(serde::Serialize)]
+pub struct CsctrlSeriesResult {
+    pub server_address: String,
+    pub team_a_name: String,
+    pub team_b_name: String,
+    pub team_a_maps_won: u8,
+    pub team_b_maps_won: u8,
+    pub winner: String,
+    pub maps: Vec<CsctrlSeriesMapResult>,
+    pub veto: Vec<CsctrlVetoAction>,
 }
 
 #[derive(Clone, Eq, Hash, PartialEq)]
@@ -128,4 +667,20 @@ pub enum CsctrlLogType {
     Invalid,
     PlayerSay,
     PlayerSwitchTeam,
+    RoundEnd,
+    TeamScored,
+    MatchStatusScore,
+    FreezePeriodStart,
+    RoundStart,
+    ServerCvar,
+    PlayerKilled,
+    PlayerSuicide,
+    PlayerAssisted,
+    PlayerAttacked,
+    PlayerConnected,
+    PlayerValidated,
+    PlayerEnteredGame,
+    PlayerDisconnected,
+    GameOver,
+    MapStarted,
 }
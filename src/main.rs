@@ -5,6 +5,8 @@ mod system;
 mod commands;
 mod rcon;
 
+use clap::Parser;
+
 #[derive(clap::Parser)]
 #[command(author, version, about, long_about = None)]
 struct ClapParser {
@@ -12,20 +14,25 @@ struct ClapParser {
     #[arg(long)]
     reset: bool,
 
-    /// Disable the Terminal User Interface
+    /// Disable the Terminal User Interface and run headless until SIGINT or SIGTERM
     #[arg(long)]
     disable_terminal: bool,
+
+    /// Also write logs to stdout, e.g. for systemd/journald. Only applies with '--disable-terminal'
+    #[arg(long)]
+    log_stdout: bool,
 }
 
-fn main() {
+#[tokio::main]
+async fn main() {
+    let command_line_args = ClapParser::parse();
     let mut csctrl = csctrl::csctrl::Csctrl::csctrl();
-    let _tracing_guard = system::utilities::configure_tracing(&csctrl.csctrl_config.tracing_env_filter);
+    // Stdout belongs to the terminal ui while it's active. The guards flush buffered logs when main returns
+    let log_to_stdout = command_line_args.log_stdout && command_line_args.disable_terminal;
+    let _tracing_guards = system::utilities::configure_tracing(&csctrl.csctrl_config.tracing_env_filter, log_to_stdout);
     system::utilities::ensure_directories_exist();
 
     csctrl.init();
-    while !csctrl.has_requested_exit() {
-        csctrl.tick();
-    }
-
-    csctrl.shutdown();
+    csctrl.run().await;
+    csctrl.shutdown().await;
 }
@@ -1,12 +1,28 @@
+use std::time::{Duration, Instant};
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
-use crate::rcon::packet::{RconPacket, RconPacketType};
+use crate::rcon::error::RconError;
+use crate::rcon::packet::{MAX_OUTGOING_PACKET_SIZE, MIN_PACKET_SIZE, RconPacket, RconPacketType};
 
+const RESPONSE_TIMEOUT_SECS: u64 = 5;
+const KEEPALIVE_INTERVAL_SECS: u64 = 30;
+const MAX_RECONNECT_BACKOFF_SECS: u64 = 30;
+/// Always answered with output, unlike an empty command
+const TERMINATING_COMMAND: &str = "echo CsctrlTerminatingRconCommand";
+
+/// A single authenticated TCP connection kept open between commands. When it drops it is
+/// reestablished on the next command, waiting longer after every failed attempt
 pub struct RconConnection {
     address: String,
     password: String,
     current_packet_id: i32,
     is_valid: bool,
+    tcp_stream: Option<TcpStream>,
+    last_activity: Instant,
+    failed_connection_attempts: u32,
+    next_connection_attempt: Option<Instant>,
+    /// Connections established so far. It changes when a dropped connection is reestablished, which can mean the server restarted
+    connection_count: u32,
 }
 
 impl RconConnection {
@@ -16,103 +32,180 @@ impl RconConnection {
             password: password.to_string(),
             current_packet_id: -1,
             is_valid: false,
+            tcp_stream: None,
+            last_activity: Instant::now(),
+            failed_connection_attempts: 0,
+            next_connection_attempt: None,
+            connection_count: 0,
         }
     }
 
-    async fn get_tcp_stream(&self) -> Result<TcpStream, String> {
-        return match tokio::net::TcpStream::connect(&self.address).await {
-            Ok(stream) => {
-                Ok(stream)
-            }
-            Err(error) => {
-                Err(format!("Can't establish a TCP stream to the server. Error: {}", error))
-            }
+    async fn get_tcp_stream(&self) -> Result<TcpStream, RconError> {
+        return match tokio::time::timeout(Duration::from_secs(RESPONSE_TIMEOUT_SECS), tokio::net::TcpStream::connect(&self.address)).await {
+            Ok(Ok(stream)) => { Ok(stream) }
+            Ok(Err(error)) => { Err(RconError::ConnectionFailed(error.to_string())) }
+            Err(_) => { Err(RconError::ConnectionFailed("Connecting timed out".to_string())) }
         };
     }
 
-    async fn authenticate(&mut self, tcp_stream: &mut TcpStream) -> bool {
-        if self.send_packet(tcp_stream, RconPacketType::Auth, &self.password.clone()).await < 0 {
-            tracing::error!("Failed authentication: can't send authentication packet");
-            return false;
-        }
+    /// The server answers an auth packet with an empty response value followed by the auth response,
+    /// whose id is -1 when the password is wrong
+    async fn authenticate(&mut self, tcp_stream: &mut TcpStream) -> Result<(), RconError> {
+        self.send_packet(tcp_stream, RconPacketType::Auth, &self.password.clone()).await?;
 
-        let start_time = std::time::SystemTime::now();
+        let start_time = Instant::now();
         let received_packet = loop {
-            if start_time.elapsed().unwrap().as_secs() > 5 {
-                tracing::error!("Failed authentication: receiving response timed out");
-                return false;
+            if start_time.elapsed().as_secs() > RESPONSE_TIMEOUT_SECS {
+                return Err(RconError::Timeout);
             }
-            let received_packet = self.receive_packet(tcp_stream).await;
+            let received_packet = self.receive_packet(tcp_stream).await?;
             if received_packet.get_type() == RconPacketType::AuthResponse {
                 break received_packet
             }
         };
 
         if received_packet.is_error() {
-            tracing::error!("Failed authentication: the provided password is incorrect");
-            return false;
+            return Err(RconError::AuthenticationFailed);
         }
 
-        return true;
+        return Ok(());
     }
 
-    pub async fn execute_command(&mut self, command: &str) -> Result<String, String> {
-        let mut tcp_stream = match self.get_tcp_stream().await {
-            Ok(stream) => { stream }
+    /// Connects and authenticates unless a connection is already open or the reconnect backoff hasn't passed yet
+    async fn connect(&mut self) -> Result<(), RconError> {
+        if self.tcp_stream.is_some() { return Ok(()); }
+
+        if let Some(next_connection_attempt) = self.next_connection_attempt {
+            let now = Instant::now();
+            if now < next_connection_attempt {
+                return Err(RconError::ConnectionFailed(format!("Retrying in {} seconds", (next_connection_attempt - now).as_secs() + 1)));
+            }
+        }
+
+        let connection_result = match self.get_tcp_stream().await {
+            Ok(mut tcp_stream) => {
+                match self.authenticate(&mut tcp_stream).await {
+                    Ok(_) => { Ok(tcp_stream) }
+                    Err(error) => { Err(error) }
+                }
+            }
+            Err(error) => { Err(error) }
+        };
+
+        return match connection_result {
+            Ok(tcp_stream) => {
+                tracing::debug!("Rcon connection to '{}' established", self.address);
+                self.tcp_stream = Some(tcp_stream);
+                self.is_valid = true;
+                self.last_activity = Instant::now();
+                self.failed_connection_attempts = 0;
+                self.next_connection_attempt = None;
+                self.connection_count = self.connection_count.wrapping_add(1);
+                Ok(())
+            }
             Err(error) => {
-                tracing::error!(error);
-                return Err(error.to_string());
+                self.is_valid = false;
+                self.failed_connection_attempts = self.failed_connection_attempts.saturating_add(1);
+                let backoff_secs = 2u64.saturating_pow(self.failed_connection_attempts - 1).min(MAX_RECONNECT_BACKOFF_SECS);
+                self.next_connection_attempt = Some(Instant::now() + Duration::from_secs(backoff_secs));
+                Err(error)
             }
         };
-        let authenticated = self.authenticate(&mut tcp_stream).await;
-        if !authenticated {
-            return Err("Failed command execution: can't authenticate".to_string());
-        }
+    }
+
+    fn disconnect(&mut self) {
+        self.tcp_stream = None;
+        self.is_valid = false;
+    }
 
-        if self.send_packet(&mut tcp_stream, RconPacketType::ExecCommand, command).await < 0 {
-            return Err("Failed command execution: can't send command packet".to_string());
+    pub async fn execute_command(&mut self, command: &str) -> Result<String, RconError> {
+        if command.len() as i32 + MIN_PACKET_SIZE > MAX_OUTGOING_PACKET_SIZE {
+            return Err(RconError::CommandTooLong(command.len()));
         }
 
-        let empty_request_id = self.send_packet(&mut tcp_stream, RconPacketType::ExecCommand, "echo CsctrlTerminatingRconCommand").await;
-        if empty_request_id < 0 {
-            return Err("Failed command execution: can't send empty command packet".to_string());
+        // A connection that sat idle may have been closed by the server without us noticing. The command is only sent
+        // again when writing it failed, once it reached the server a second run could repeat actions like mp_swapteams
+        let was_connected = self.tcp_stream.is_some();
+        self.connect().await?;
+
+        let result = self.execute_command_on_stream(command).await;
+        if result.as_ref().is_err_and(|error| error.is_connection_error()) {
+            self.disconnect();
+            if was_connected && matches!(result, Err(RconError::SendFailed(_))) {
+                tracing::debug!("Rcon connection to '{}' was lost, reconnecting", self.address);
+                self.connect().await?;
+                let retry_result = self.execute_command_on_stream(command).await;
+                if retry_result.is_err() { self.disconnect(); }
+                return retry_result;
+            }
         }
+        return result;
+    }
+
+    async fn execute_command_on_stream(&mut self, command: &str) -> Result<String, RconError> {
+        let mut tcp_stream = match self.tcp_stream.take() {
+            Some(tcp_stream) => { tcp_stream }
+            None => { return Err(RconError::NotConnected); }
+        };
+        let result = self.send_command_and_receive_response(&mut tcp_stream, command).await;
+        self.tcp_stream = Some(tcp_stream);
+        self.last_activity = Instant::now();
+        return result;
+    }
+
+    /// Big outputs like 'status' or 'cvarlist' are split over several packets with the command's id.
+    /// The server handles commands in order, so once the echo sent right after it is answered
+    /// every packet of the response has arrived. The echo is recognized by its id, not by its output
+    async fn send_command_and_receive_response(&mut self, tcp_stream: &mut TcpStream, command: &str) -> Result<String, RconError> {
+        let command_request_id = match self.send_packet(tcp_stream, RconPacketType::ExecCommand, command).await {
+            Ok(command_request_id) => { command_request_id }
+            Err(RconError::Io(error)) => { return Err(RconError::SendFailed(error)); }
+            Err(error) => { return Err(error); }
+        };
+        let terminating_request_id = self.send_packet(tcp_stream, RconPacketType::ExecCommand, TERMINATING_COMMAND).await?;
 
         let mut response_body = "".to_string();
-        let start_time = std::time::SystemTime::now();
+        let start_time = Instant::now();
         loop {
-            if start_time.elapsed().unwrap().as_secs() > 5 {
-                return Err("Failed command execution: didn't receive response before timeout".to_string());
+            if start_time.elapsed().as_secs() > RESPONSE_TIMEOUT_SECS {
+                return Err(RconError::Timeout);
             }
 
-            let received_packet = self.receive_packet(&mut tcp_stream).await;
-            if received_packet.get_body() == "CsctrlTerminatingRconCommand\n" {
-                tcp_stream.shutdown().await.expect("Failed to shutdown TCP stream after sending a packet");
+            let received_packet = self.receive_packet(tcp_stream).await?;
+            if received_packet.get_type() != RconPacketType::ResponseValue { continue; }
+
+            if received_packet.get_id() == terminating_request_id {
                 return Ok(response_body);
             }
 
-            response_body += received_packet.get_body();
+            if received_packet.get_id() == command_request_id {
+                response_body += received_packet.get_body();
+            }
         }
     }
 
-    async fn send_packet(&mut self, tcp_stream: &mut TcpStream, packet_type: RconPacketType, body: &str) -> i32 {
-        let id = self.get_new_packet_id();
-        let packet = RconPacket::new(id, packet_type, body.to_string());
-        let serialized_packet = packet.serialize();
+    /// Sends an empty command when the connection has been idle for a while so it isn't dropped by the server
+    pub async fn keepalive(&mut self) {
+        if self.tcp_stream.is_none() || self.last_activity.elapsed().as_secs() < KEEPALIVE_INTERVAL_SECS { return; }
 
-        match tcp_stream.write_all(&serialized_packet).await {
-            Ok(_) => { }
-            Err(error) => {
-                tracing::error!("Can't send packet. Error: {}", error);
-                return -999;
-            }
-        };
+        if let Err(error) = self.execute_command_on_stream("").await {
+            tracing::debug!("Rcon keepalive to '{}' failed, reconnecting on the next command. {}", self.address, error);
+            self.disconnect();
+        }
+    }
 
-        return id;
+    async fn send_packet(&mut self, tcp_stream: &mut TcpStream, packet_type: RconPacketType, body: &str) -> Result<i32, RconError> {
+        let id = self.get_new_packet_id();
+        let packet = RconPacket::new(id, packet_type, body.to_string());
+        tcp_stream.write_all(&packet.serialize()).await?;
+        return Ok(id);
     }
 
-    async fn receive_packet(&mut self, tcp_stream: &mut TcpStream) -> RconPacket {
-        return RconPacket::deserialize(tcp_stream).await;
+    async fn receive_packet(&mut self, tcp_stream: &mut TcpStream) -> Result<RconPacket, RconError> {
+        return match tokio::time::timeout(Duration::from_secs(RESPONSE_TIMEOUT_SECS), RconPacket::deserialize(tcp_stream)).await {
+            Ok(received_packet) => { received_packet }
+            Err(_) => { Err(RconError::Timeout) }
+        };
     }
 
     fn get_new_packet_id(&mut self) -> i32 {
@@ -123,4 +216,59 @@ impl RconConnection {
     }
 
     pub fn get_is_valid(&self) -> bool { return self.is_valid; }
+    pub fn get_connection_count(&self) -> u32 { return self.connection_count; }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn send_response(tcp_stream: &mut TcpStream, id: i32, packet_type: RconPacketType, body: &str) {
+        tcp_stream.write_all(&RconPacket::new(id, packet_type, body.to_string()).serialize()).await.unwrap();
+    }
+
+    /// Accepts one connection, authenticates it and answers the next command with `response_parts`,
+    /// each in its own packet, before answering the terminating echo
+    async fn start_stand_in(response_parts: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut tcp_stream, _) = listener.accept().await.unwrap();
+            let auth_packet = RconPacket::deserialize(&mut tcp_stream).await.unwrap();
+            send_response(&mut tcp_stream, auth_packet.get_id(), RconPacketType::ResponseValue, "").await;
+            send_response(&mut tcp_stream, auth_packet.get_id(), RconPacketType::AuthResponse, "").await;
+
+            let command_packet = RconPacket::deserialize(&mut tcp_stream).await.unwrap();
+            let terminating_packet = RconPacket::deserialize(&mut tcp_stream).await.unwrap();
+            assert_eq!(terminating_packet.get_body(), TERMINATING_COMMAND);
+            for response_part in response_parts {
+                send_response(&mut tcp_stream, command_packet.get_id(), RconPacketType::ResponseValue, response_part).await;
+            }
+            send_response(&mut tcp_stream, terminating_packet.get_id(), RconPacketType::ResponseValue, "CsctrlTerminatingRconCommand\n").await;
+
+            // Keeps the connection open until the client is done
+            let _ = RconPacket::deserialize(&mut tcp_stream).await;
+        });
+        return address;
+    }
+
+    #[tokio::test]
+    async fn reassembles_a_response_split_over_several_packets() {
+        let address = start_stand_in(vec!["hostname: csctrl\n", "map     : de_inferno\n", "players : 0 humans, 0 bots (10 max)\n"]).await;
+        let mut rcon_connection = RconConnection::create_rcon_connection(&address, "password");
+
+        let response = rcon_connection.execute_command("status").await.unwrap();
+        assert_eq!(response, "hostname: csctrl\nmap     : de_inferno\nplayers : 0 humans, 0 bots (10 max)\n");
+        assert!(rcon_connection.get_is_valid());
+        assert_eq!(rcon_connection.get_connection_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn answers_commands_without_output_with_an_empty_response() {
+        let address = start_stand_in(vec![]).await;
+        let mut rcon_connection = RconConnection::create_rcon_connection(&address, "password");
+
+        assert_eq!(rcon_connection.execute_command("mp_warmup_end").await.unwrap(), "");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,45 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum RconError {
+    /// The TCP stream failed while reading or writing, the connection has to be reestablished
+    Io(std::io::Error),
+    /// Writing the command failed, so it never reached the server and can be sent again
+    SendFailed(std::io::Error),
+    ConnectionFailed(String),
+    AuthenticationFailed,
+    NotConnected,
+    Timeout,
+    InvalidPacketSize(i32),
+    MalformedPacket(String),
+    CommandTooLong(usize),
+}
+
+impl RconError {
+    /// Whether the stream is left in an unknown state and shouldn't be used for another command
+    pub fn is_connection_error(&self) -> bool {
+        return !matches!(self, RconError::CommandTooLong(_));
+    }
+}
+
+impl fmt::Display for RconError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            RconError::Io(error) => { write!(formatter, "Rcon connection error. {}", error) }
+            RconError::SendFailed(error) => { write!(formatter, "Couldn't send the command to the server. {}", error) }
+            RconError::ConnectionFailed(error) => { write!(formatter, "Can't establish a TCP stream to the server. {}", error) }
+            RconError::AuthenticationFailed => { write!(formatter, "Failed authentication: the provided password is incorrect") }
+            RconError::NotConnected => { write!(formatter, "Not connected to the server") }
+            RconError::Timeout => { write!(formatter, "Didn't receive a response before timeout") }
+            RconError::InvalidPacketSize(size) => { write!(formatter, "Received a packet with invalid size {}", size) }
+            RconError::MalformedPacket(reason) => { write!(formatter, "Received a malformed packet. {}", reason) }
+            RconError::CommandTooLong(length) => { write!(formatter, "Command of {} bytes is longer than a packet allows", length) }
+        };
+    }
+}
+
+impl From<std::io::Error> for RconError {
+    fn from(error: std::io::Error) -> Self {
+        return RconError::Io(error);
+    }
+}
\ No newline at end of file
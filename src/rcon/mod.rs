@@ -1,2 +1,3 @@
 pub mod connection;
-pub mod packet;
+pub mod error;
+pub mod packet;
\ No newline at end of file
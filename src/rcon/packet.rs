@@ -1,4 +1,12 @@
 use tokio::io::AsyncReadExt;
+use crate::rcon::error::RconError;
+
+/// Id, type and the two null terminators of an empty body
+pub const MIN_PACKET_SIZE: i32 = 10;
+/// The protocol limits packets sent to the server to 4096 bytes
+pub const MAX_OUTGOING_PACKET_SIZE: i32 = 4096;
+/// Responses are split into packets by the server, anything bigger than this is a corrupted stream
+pub const MAX_INCOMING_PACKET_SIZE: i32 = 65536;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum RconPacketType {
@@ -58,66 +66,39 @@ impl RconPacket {
         return buffer;
     }
 
-    pub async fn deserialize<T: Unpin + tokio::io::AsyncRead>(incoming_stream :&mut T) -> RconPacket {
+    /// Reads a single packet. A size outside of the protocol bounds means the stream can't be trusted anymore
+    pub async fn deserialize<T: Unpin + tokio::io::AsyncRead>(incoming_stream :&mut T) -> Result<RconPacket, RconError> {
         let mut buffer = [0u8; 4];
 
-        let error_packet = RconPacket::new(-999, RconPacketType::Undefined(-90), "".to_string());
-        let size = match incoming_stream.read_exact(&mut buffer).await {
-            Ok(_) => { i32::from_le_bytes(buffer) }
-            Err(error) => {
-                tracing::error!("Can't deserialize size of packet. Error: {}", error);
-                return error_packet;
-            }
-        };
-
-        let id = match incoming_stream.read_exact(&mut buffer).await {
-            Ok(_) => { i32::from_le_bytes(buffer) }
-            Err(error) => {
-                tracing::error!("Can't deserialize id of packet. Error: {}", error);
-                return error_packet;
-            }
-        };
-
-        let packet_type = match incoming_stream.read_exact(&mut buffer).await {
-            Ok(_) => { i32::from_le_bytes(buffer) }
-            Err(error) => {
-                tracing::error!("Can't deserialize type of packet. Error: {}", error);
-                return error_packet;
-            }
-        };
-
-        let body_size = size - 10;
-        let mut body_buffer = vec![0u8; body_size as usize];
-
-        let body = match incoming_stream.read_exact(&mut body_buffer).await {
-            Ok(_) => { match String::from_utf8(body_buffer) {
-                Ok(b_buffer) => { b_buffer }
-                Err(error) => {
-                    tracing::error!("Can't read string from deserialized string buffer. Error: {}", error);
-                    return error_packet;
-                }
-            }}
-            Err(error) => {
-                tracing::error!("Can't deserialize body of packet. Error: {}", error);
-                return error_packet;
-            }
-        };
-        
-        let mut terminating_buffer = [0u8; 2];
-        match incoming_stream.read_exact(&mut terminating_buffer).await {
-            Ok(_) => {}
-            Err(error) => {
-                tracing::error!("Can't deserialize terminating zeros of packet. Error: {}", error);
-                return error_packet;
-            }
+        incoming_stream.read_exact(&mut buffer).await?;
+        let size = i32::from_le_bytes(buffer);
+        if !(MIN_PACKET_SIZE..=MAX_INCOMING_PACKET_SIZE).contains(&size) {
+            return Err(RconError::InvalidPacketSize(size));
         }
-        
-        RconPacket {
+
+        incoming_stream.read_exact(&mut buffer).await?;
+        let id = i32::from_le_bytes(buffer);
+
+        incoming_stream.read_exact(&mut buffer).await?;
+        let packet_type = i32::from_le_bytes(buffer);
+
+        // Body and its null terminator plus the empty string terminator
+        let mut body_buffer = vec![0u8; (size - 8) as usize];
+        incoming_stream.read_exact(&mut body_buffer).await?;
+        if body_buffer[body_buffer.len() - 2..] != [0x00, 0x00] {
+            return Err(RconError::MalformedPacket("Packet isn't null terminated".to_string()));
+        }
+        body_buffer.truncate(body_buffer.len() - 2);
+
+        // Server output can contain player names that aren't valid UTF-8, which shouldn't fail the whole response
+        let body = String::from_utf8_lossy(&body_buffer).to_string();
+
+        return Ok(RconPacket {
             size,
             id,
             packet_type: RconPacketType::from_i32(packet_type, true),
             body,
-        }
+        });
     }
 
     pub fn get_packet_as_string(&self) -> String {
@@ -133,4 +114,77 @@ impl RconPacket {
     pub fn get_type(&self) -> RconPacketType { self.packet_type }
     pub fn get_body(&self) -> &str { &self.body }
     pub fn is_error(&self) -> bool { return self.id < 0; }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet_bytes(size: i32, id: i32, packet_type: i32, body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes.extend_from_slice(&id.to_le_bytes());
+        bytes.extend_from_slice(&packet_type.to_le_bytes());
+        bytes.extend_from_slice(body);
+        return bytes;
+    }
+
+    #[tokio::test]
+    async fn reads_a_response_split_over_several_packets() {
+        let mut stream = vec![];
+        stream.extend(packet_bytes(10 + 22, 7, 0, b"hostname: csctrl\nmap  \0\0"));
+        stream.extend(packet_bytes(10 + 13, 7, 0, b"  : de_dust2\n\0\0"));
+        stream.extend(packet_bytes(10 + 3, 8, 0, b"end\0\0"));
+        let mut incoming_stream = stream.as_slice();
+
+        let mut response_body = "".to_string();
+        loop {
+            let received_packet = RconPacket::deserialize(&mut incoming_stream).await.unwrap();
+            assert_eq!(received_packet.get_type(), RconPacketType::ResponseValue);
+            if received_packet.get_id() == 8 { break; }
+            assert_eq!(received_packet.get_id(), 7);
+            response_body += received_packet.get_body();
+        }
+        assert_eq!(response_body, "hostname: csctrl\nmap    : de_dust2\n");
+        assert!(incoming_stream.is_empty());
+    }
+
+    #[tokio::test]
+    async fn round_trips_serialized_packets() {
+        let packet = RconPacket::new(3, RconPacketType::ExecCommand, "status".to_string());
+        let serialized_packet = packet.serialize();
+        let received_packet = RconPacket::deserialize(&mut serialized_packet.as_slice()).await.unwrap();
+        assert_eq!(received_packet.get_id(), 3);
+        // Type 2 coming from the server is read as an auth response
+        assert_eq!(received_packet.get_type(), RconPacketType::AuthResponse);
+        assert_eq!(received_packet.get_body(), "status");
+    }
+
+    #[tokio::test]
+    async fn rejects_packets_smaller_than_the_minimum_size() {
+        let stream = packet_bytes(9, 1, 0, b"\0\0");
+        let result = RconPacket::deserialize(&mut stream.as_slice()).await;
+        assert!(matches!(result, Err(RconError::InvalidPacketSize(9))));
+    }
+
+    #[tokio::test]
+    async fn rejects_packets_bigger_than_the_maximum_size() {
+        let stream = packet_bytes(MAX_INCOMING_PACKET_SIZE + 1, 1, 0, b"\0\0");
+        let result = RconPacket::deserialize(&mut stream.as_slice()).await;
+        assert!(matches!(result, Err(RconError::InvalidPacketSize(65537))));
+    }
+
+    #[tokio::test]
+    async fn rejects_packets_without_null_terminators() {
+        let stream = packet_bytes(10 + 4, 1, 0, b"body\0x");
+        let result = RconPacket::deserialize(&mut stream.as_slice()).await;
+        assert!(matches!(result, Err(RconError::MalformedPacket(_))));
+    }
+
+    #[tokio::test]
+    async fn fails_on_a_truncated_packet() {
+        let stream = packet_bytes(10 + 4, 1, 0, b"bo");
+        let result = RconPacket::deserialize(&mut stream.as_slice()).await;
+        assert!(matches!(result, Err(RconError::Io(_))));
+    }
 }
\ No newline at end of file
@@ -2,6 +2,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use clap::Parser;
 use rand::Rng;
+use tracing_subscriber::layer::SubscriberExt;
 use crate::ClapParser;
 use crate::csctrl::types::CsctrlConfig;
 
@@ -42,35 +43,108 @@ fn generate_default_config() -> CsctrlConfig {
     tracing::info!("Creating config file 'csctrl.json' at executable directory");
 
     let config = CsctrlConfig {
+        api_token: rand::thread_rng().sample_iter(&rand::distributions::Alphanumeric).take(64).map(char::from).collect(),
         chat_signature: "csctrl".to_string(),
+        cs_log_address: "".to_string(),
         cs_listen_path: "/cslog".to_string(),
         rest_api_address: "0.0.0.0:27016".to_string(),
         secret: rand::thread_rng().sample_iter(&rand::distributions::Alphanumeric).take(64).map(char::from).collect(),
         servers: vec![],
         tracing_env_filter: "csctrl=info".to_string(),
+        webhooks: vec![],
     };
 
     write_config(&config);
     return config;
 }
 
-pub fn configure_tracing(env_filter: &str) -> tracing_appender::non_blocking::WorkerGuard {
+pub fn configure_tracing(env_filter: &str, log_to_stdout: bool) -> Vec<tracing_appender::non_blocking::WorkerGuard> {
     let mut csctrl_binary_path = std::env::current_exe().unwrap();
     csctrl_binary_path.pop();
     csctrl_binary_path.push("logs");
 
     let timestamp = chrono::Local::now().format("%Y-%m-%d---%H-%M-%S");
     let file_appender = tracing_appender::rolling::never(csctrl_binary_path, format!("csctrl_{}.log", timestamp));
-    let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
+    let (non_blocking, file_guard) = tracing_appender::non_blocking(file_appender);
+    let mut guards = vec![file_guard];
 
-    tracing::subscriber::set_global_default(tracing_subscriber::fmt().with_writer(non_blocking)
+    let file_layer = tracing_subscriber::fmt::layer()
+        .with_writer(non_blocking)
         .with_target(false)
-        .with_env_filter(env_filter)
         .with_ansi(false)
-        .with_thread_names(true)
-        .finish()).expect("Failed tracing subscriber creation");
+        .with_thread_names(true);
 
-    return _guard;
+    // Journald adds its own timestamps
+    let stdout_layer = if log_to_stdout {
+        let (non_blocking_stdout, stdout_guard) = tracing_appender::non_blocking(std::io::stdout());
+        guards.push(stdout_guard);
+        Some(tracing_subscriber::fmt::layer()
+            .with_writer(non_blocking_stdout)
+            .with_target(false)
+            .with_ansi(false)
+            .without_time())
+    } else {
+        None
+    };
+
+    tracing::subscriber::set_global_default(tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::new(env_filter))
+        .with(file_layer)
+        .with(stdout_layer)).expect("Failed tracing subscriber creation");
+
+    return guards;
+}
+
+/// Resolves once csctrl is asked to stop with SIGINT or, on unix, SIGTERM as sent by service managers
+pub async fn wait_for_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate_signal = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(terminate_signal) => { terminate_signal }
+            Err(error) => {
+                tracing::error!("Can't listen for SIGTERM. Error: {}", error);
+                let _ = tokio::signal::ctrl_c().await;
+                return;
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => { tracing::info!("Received SIGINT"); }
+            _ = terminate_signal.recv() => { tracing::info!("Received SIGTERM"); }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        tracing::info!("Received Ctrl-C");
+    }
+}
+
+/// Writes a JSON file to 'logs/matches' prefixed by the current time. Returns the path of the written file
+pub fn write_match_log<T: serde::Serialize>(name: &str, value: &T) -> Result<PathBuf, String> {
+    let mut match_log_path = get_csctrl_config_file_path();
+    match_log_path.pop();
+    match_log_path.push("logs/matches");
+
+    let timestamp = chrono::Local::now().format("%Y-%m-%d---%H-%M-%S");
+    let file_name = format!("{}_{}.json", timestamp, name);
+    let sanitized_file_name: String = file_name.chars().map(|char| if char.is_ascii_alphanumeric() || "-_.".contains(char) { char } else { '_' }).collect();
+    match_log_path.push(sanitized_file_name);
+
+    let file = match std::fs::OpenOptions::new().write(true).truncate(true).create(true).open(&match_log_path) {
+        Ok(file) => { file }
+        Err(error) => { return Err(format!("Can't create match log file '{}'. Error: {}", match_log_path.to_str().unwrap(), error)); }
+    };
+
+    return match serde_json::to_writer_pretty(file, value) {
+        Ok(_) => { Ok(match_log_path) }
+        Err(error) => { Err(format!("Can't write match log to file '{}'. Error: {}", match_log_path.to_str().unwrap(), error)) }
+    };
+}
+
+pub fn get_unix_timestamp_secs() -> u64 {
+    return std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
 }
 
 pub fn ensure_directories_exist() {
@@ -86,7 +160,7 @@ pub fn ensure_directories_exist() {
     for required_directory in required_directories {
         let directory = Path::new(&csctrl_binary_path).join(required_directory);
         if directory.exists() {
-            return;
+            continue;
         }
         fs::create_dir_all(directory).expect(&format!("Failed to create the required directory '{}'", required_directory));
     }
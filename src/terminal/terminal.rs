@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::io::{stdout, Stdout};
 use crossterm::ExecutableCommand;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
@@ -16,19 +17,26 @@ use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
 use ratatui::widgets::GraphType::Line;
 use tracing_subscriber::fmt::format;
 use crate::ClapParser;
-use crate::csctrl::types::CsctrlDataParent;
+use crate::csctrl::types::{CsctrlDataParent, CsctrlDataPlayer, CsctrlDataServer, CsctrlMatchStatus, CsctrlMessage};
+use crate::csctrl::log_events::get_player_key;
+use crate::system::utilities::get_unix_timestamp_secs;
 
 struct TerminalUiState {
     input_box: String,
     last_type_time_secs: u64,
-    selected_server_address: String
+    selected_server_address: String,
+    logs: VecDeque<String>,
 }
 
+/// Oldest lines are dropped past this so long rcon outputs don't grow the logs forever
+const MAX_LOG_LINES: usize = 500;
+
 pub struct Terminal {
     terminal_ui: OnceCell<ratatui::Terminal<CrosstermBackend<Stdout>>>,
     is_terminal_active: bool,
     terminal_ui_state: TerminalUiState,
     cached_server_data: CsctrlDataParent,
+    event_receiver: Option<tokio::sync::mpsc::UnboundedReceiver<Event>>,
 }
 
 impl Terminal {
@@ -38,10 +46,12 @@ impl Terminal {
                 input_box: "".to_string(),
                 last_type_time_secs: 0,
                 selected_server_address: "".to_string(),
+                logs: VecDeque::new(),
             },
             terminal_ui: OnceCell::new(),
             is_terminal_active: false,
             cached_server_data: CsctrlDataParent { servers: Default::default() },
+            event_receiver: None,
         }
     }
     
@@ -55,11 +65,29 @@ impl Terminal {
         });
 
         self.is_terminal_active = true;
+
+        // Reading terminal input blocks, so it gets its own thread that forwards every event to the main loop
+        let (event_sender, event_receiver) = tokio::sync::mpsc::unbounded_channel();
+        self.event_receiver = Some(event_receiver);
+        std::thread::Builder::new().name("[Terminal]".to_string()).spawn(move || {
+            loop {
+                let event = match crossterm::event::read() {
+                    Ok(event) => { event }
+                    Err(error) => {
+                        tracing::error!("Can't read terminal input. Error: {}", error);
+                        return;
+                    }
+                };
+                if event_sender.send(event).is_err() { return; }
+            }
+        }).unwrap();
+    }
+
+    pub fn take_event_receiver(&mut self) -> Option<tokio::sync::mpsc::UnboundedReceiver<Event>> {
+        return self.event_receiver.take();
     }
-    
-    pub fn tick(&mut self) {
-        self.handle_events();
 
+    pub fn draw(&mut self) {
         self.terminal_ui.get_mut().unwrap().draw(|frame| {
             ui(&mut self.terminal_ui_state, &mut self.cached_server_data, frame);
         }).unwrap();
@@ -69,10 +97,8 @@ impl Terminal {
         self.cached_server_data = new_server_data;
     }
 
-    fn handle_events(&mut self) {
-        if !crossterm::event::poll(Default::default()).unwrap() { return; }
-
-        if let Event::Key(key) = crossterm::event::read().unwrap() {
+    pub fn handle_event(&mut self, event: Event) {
+        if let Event::Key(key) = event {
             match key.code {
                 KeyCode::Char(value) => {
                     if !validate_input_char(&value) { return; }
@@ -88,10 +114,14 @@ impl Terminal {
                 KeyCode::Enter => {
                     if key.kind != KeyEventKind::Press { return; }
 
-                    let input = format!("<csctrlseptarget>{}<csctrlseptarget>{}", self.terminal_ui_state.selected_server_address, self.terminal_ui_state.input_box.to_string());
-                    if input.is_empty() { return; }
+                    if self.terminal_ui_state.input_box.is_empty() { return; }
+                    let command_message = CsctrlMessage::Command {
+                        target_address: self.terminal_ui_state.selected_server_address.clone(),
+                        command: self.terminal_ui_state.input_box.to_string(),
+                        responder: None,
+                    };
                     self.terminal_ui_state.input_box.clear();
-                    crate::csctrl::csctrl::get_command_messenger().write().unwrap().push_back(input);
+                    let _ = crate::csctrl::csctrl::get_message_sender().send(command_message);
                 }
                 KeyCode::Esc => { self.close_terminal(); }
                 _ => {}
@@ -110,12 +140,19 @@ impl Terminal {
         self.terminal_ui_state.selected_server_address = new_selected_server.to_string();
     }
 
+    pub fn add_log(&mut self, log_line: &str) {
+        self.terminal_ui_state.logs.push_back(log_line.to_string());
+        while self.terminal_ui_state.logs.len() > MAX_LOG_LINES {
+            self.terminal_ui_state.logs.pop_front();
+        }
+    }
+
     pub fn is_terminal_active(&self) -> &bool {
         return &self.is_terminal_active;
     }
     
-    pub fn shutdown(&self) {
-
+    pub fn shutdown(&mut self) {
+        if self.is_terminal_active { self.close_terminal(); }
     }
 }
 
@@ -125,6 +162,37 @@ fn validate_input_char(char: &char) -> bool {
     return true;
 }
 
+fn get_player_scoreboard_line(server_data: &CsctrlDataServer, player: &CsctrlDataPlayer) -> String {
+    let player_line = match server_data.player_stats.get(&get_player_key(&player.steam_id, &player.name)) {
+        Some(stats) => {
+            format!("{} {}/{}/{} ADR {:.0} HS {:.0}% K/D {:.2}", player.name, stats.kills, stats.assists, stats.deaths,
+                    stats.average_damage_per_round(server_data.rounds_played), stats.headshot_percentage(), stats.kill_death_ratio())
+        }
+        None => { player.name.to_string() }
+    };
+
+    let status_player = server_data.server_info.players.iter()
+        .find(|status_player| !status_player.is_bot && status_player.steam_id == Some(player.steam_id));
+    return match status_player {
+        Some(status_player) => { format!("{} {}ms", player_line, status_player.ping) }
+        None => { player_line }
+    };
+}
+
+fn get_server_health_text(server_data: &CsctrlDataServer) -> String {
+    if server_data.is_online {
+        return match server_data.health.latency_ms {
+            Some(latency_ms) => { format!("({}ms)", latency_ms) }
+            None => { "(no rcon)".to_string() }
+        };
+    }
+
+    return match server_data.health.last_seen_at {
+        Some(last_seen_at) => { format!("(seen {}s ago)", get_unix_timestamp_secs().saturating_sub(last_seen_at)) }
+        None => { "(never seen)".to_string() }
+    };
+}
+
 fn ui(state: &mut TerminalUiState, data: &mut CsctrlDataParent, frame: &mut Frame<CrosstermBackend<Stdout>>) {
     let terminal_height = frame.size().height;
 
@@ -185,14 +253,15 @@ fn ui(state: &mut TerminalUiState, data: &mut CsctrlDataParent, frame: &mut Fram
         "".into(),
     ];
     for (server_address, server_data) in &data.servers {
+        let server_line = format!("{} - {} {}", server_data.config.name, server_address, get_server_health_text(server_data));
         if state.selected_server_address == server_address.to_string() {
-            server_list.push(Span::styled(format!("{} - {}", server_data.config.name, server_address), Style::default().bg(Color::Green).black().bold()).into());
+            server_list.push(Span::styled(server_line, Style::default().bg(Color::Green).black().bold()).into());
         }
         else if server_data.is_online {
-            server_list.push(Span::styled(format!("{} - {}", server_data.config.name, server_address), Style::default().green()).into());
+            server_list.push(Span::styled(server_line, Style::default().green()).into());
         }
         else {
-            server_list.push(Span::styled(format!("{} - {}", server_data.config.name, server_address), Style::default().dark_gray()).into());
+            server_list.push(Span::styled(server_line, Style::default().dark_gray()).into());
         }
         server_list.push("".into());
     }
@@ -200,8 +269,24 @@ fn ui(state: &mut TerminalUiState, data: &mut CsctrlDataParent, frame: &mut Fram
 
     frame.render_widget(Block::new().title("CSCTRL".red().bold().underlined()), layout_main[0]);
     frame.render_widget(Paragraph::new(server_list).block(servers_block).wrap(Wrap { trim: false }), layout_servers_active[0]);
-    frame.render_widget(Block::new().title("Selected server data").borders(Borders::all()), layout_active_logs[0]);
-    frame.render_widget(Block::new().title("Logs").borders(Borders::all()), layout_active_logs[1]);
+    let mut selected_server_title = "Selected server data".to_string();
+    if let Some(selected_server_data) = data.servers.get(&state.selected_server_address) {
+        selected_server_title = format!("{} - {:?}", selected_server_title, selected_server_data.status);
+        if !selected_server_data.map.is_empty() {
+            selected_server_title = format!("{} - {} - Round {}", selected_server_title, selected_server_data.map, selected_server_data.rounds_played as u16 + 1);
+        }
+        if selected_server_data.server_info.updated_at.is_some() {
+            let server_info = &selected_server_data.server_info;
+            selected_server_title = format!("{} - {}/{} players", selected_server_title, server_info.human_players, server_info.max_players);
+        }
+    }
+    frame.render_widget(Block::new().title(selected_server_title).borders(Borders::all()), layout_active_logs[0]);
+    let visible_log_amount = cmp::max(layout_active_logs[1].height as i32 - 2, 0) as usize;
+    let log_list: Vec<ratatui::prelude::Line<'_>> = state.logs.iter()
+        .skip(state.logs.len().saturating_sub(visible_log_amount))
+        .map(|log_line| log_line.as_str().into())
+        .collect();
+    frame.render_widget(Paragraph::new(log_list).block(Block::new().title("Logs").borders(Borders::all())), layout_active_logs[1]);
 
     let selected_server_data_option = data.servers.get(&state.selected_server_address);
     if selected_server_data_option.is_some() {
@@ -214,11 +299,11 @@ fn ui(state: &mut TerminalUiState, data: &mut CsctrlDataParent, frame: &mut Fram
         let mut team_t_list: Vec<ratatui::prelude::Line<'_>> = vec![];
 
         for player_ct in &selected_server_data.team_ct.players {
-            team_ct_list.push(Span::styled(&player_ct.name, Style::default()).into());
+            team_ct_list.push(Span::styled(get_player_scoreboard_line(selected_server_data, player_ct), Style::default()).into());
         }
 
         for player_t in &selected_server_data.team_t.players {
-            team_t_list.push(Span::styled(&player_t.name, Style::default()).into());
+            team_t_list.push(Span::styled(get_player_scoreboard_line(selected_server_data, player_t), Style::default()).into());
         }
 
         let team_ct_name = if !selected_server_data.team_ct.name.is_empty() {
@@ -234,15 +319,38 @@ fn ui(state: &mut TerminalUiState, data: &mut CsctrlDataParent, frame: &mut Fram
         };
 
         let team_ct_border_title = format!(" {}Team CT - {} ", team_ct_name, selected_server_data.team_ct.score);
-        let team_t_border_title = format!(" {} - Team T{} ", selected_server_data.team_ct.score, team_t_name);
+        let team_t_border_title = format!(" {} - Team T{} ", selected_server_data.team_t.score, team_t_name);
 
         let team_ct_block = Block::new().title(team_ct_border_title).border_style(Style::default().light_blue()).title_style(Style::default().light_blue()).title_alignment(Alignment::Right).borders(Borders::all());
         let team_t_block = Block::new().title(team_t_border_title).border_style(Style::default().yellow()).title_style(Style::default().yellow()).title_alignment(Alignment::Left).borders(Borders::all());
         let events_block = Block::new().title("Events").borders(Borders::all());
+        let mut events_list: Vec<ratatui::prelude::Line<'_>> = vec![];
+        if selected_server_data.status == CsctrlMatchStatus::Veto {
+            events_list.push(format!("Veto maps left: {}", selected_server_data.veto.remaining_maps.join(", ")).into());
+        }
+        for veto_action in selected_server_data.veto.actions.iter().rev() {
+            events_list.push(format!("{} {:?} {}", veto_action.team_name, veto_action.action, veto_action.map).into());
+        }
+        for pause in selected_server_data.pauses.iter().rev() {
+            let started_at = match pause.started_at {
+                Some(started_at) => { started_at }
+                None => {
+                    let pause_state = if pause.ended_at.is_none() { "waiting for freeze time" } else { "cancelled before freeze time" };
+                    events_list.push(format!("{:?} pause by {} in round {}, {}", pause.pause_type, pause.team_name, pause.round, pause_state).into());
+                    continue;
+                }
+            };
+            let pause_duration_secs = pause.ended_at.unwrap_or(get_unix_timestamp_secs()) - started_at;
+            let pause_state = if pause.ended_at.is_none() { "ongoing" } else { "ended" };
+            events_list.push(format!("{:?} pause by {} in round {}, {}s {}", pause.pause_type, pause.team_name, pause.round, pause_duration_secs, pause_state).into());
+        }
+        if !selected_server_data.last_round_winner_side.is_empty() {
+            events_list.push(format!("Round {} won by {} ({})", selected_server_data.rounds_played, selected_server_data.last_round_winner_side, selected_server_data.last_round_reason).into());
+        }
 
         frame.render_widget(Paragraph::new(team_ct_list).block(team_ct_block).alignment(Alignment::Right), layout_active_server_data[0]);
         frame.render_widget(Paragraph::new(team_t_list).block(team_t_block).alignment(Alignment::Left), layout_active_server_data[1]);
-        frame.render_widget(events_block, layout_active_server_data[2]);
+        frame.render_widget(Paragraph::new(events_list).block(events_block).wrap(Wrap { trim: false }), layout_active_server_data[2]);
     }
 
     let time_in_secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
@@ -1 +1,2 @@
-pub mod webserver;
\ No newline at end of file
+pub mod webserver;
+pub mod webhooks;
\ No newline at end of file
@@ -0,0 +1,430 @@
+use std::cell::OnceCell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::task::{JoinHandle, JoinSet};
+use tracing::Instrument;
+use std::time::Duration;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use crate::csctrl::csctrl::{get_data, get_event_broadcaster, SHUTDOWN_TIMEOUT_SECS};
+use crate::csctrl::types::{CsctrlConfig, CsctrlEvent, CsctrlMatchStatus, CsctrlServerEvent};
+use crate::system::utilities::{get_csctrl_config_file_path, get_unix_timestamp_secs};
+
+const WEBHOOK_MAX_ATTEMPTS: u32 = 5;
+const WEBHOOK_REQUEST_TIMEOUT_SECS: u64 = 10;
+
+type HttpsClient = hyper::Client<hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>;
+
+pub struct Webhooks {
+    thread_webhooks: OnceCell<JoinHandle<()>>,
+    shutdown_notify: Arc<Notify>,
+}
+
+#[derive(serde::Serialize)]
+struct WebhookPayload {
+    event: String,
+    delivery_id: String,
+    server_address: String,
+    timestamp: u64,
+    data: serde_json::Value,
+}
+
+/// One payload on its way to one receiver
+struct WebhookDelivery {
+    url: String,
+    event_name: String,
+    delivery_id: String,
+    body: String,
+    signature: String,
+    delivery_log_path: PathBuf,
+}
+
+#[derive(serde::Serialize)]
+struct WebhookDeliveryLog<'a> {
+    timestamp: u64,
+    delivery_id: &'a str,
+    event: &'a str,
+    url: &'a str,
+    attempt: u32,
+    status_code: Option<u16>,
+    error: Option<String>,
+    is_delivered: bool,
+}
+
+impl Webhooks {
+    pub fn webhooks() -> Webhooks {
+        Webhooks {
+            thread_webhooks: OnceCell::new(),
+            shutdown_notify: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn init(&self, csctrl_config: &CsctrlConfig) {
+        let global_webhooks = csctrl_config.webhooks.clone();
+        let secret = csctrl_config.secret.clone();
+        let has_webhooks = !global_webhooks.is_empty() || csctrl_config.servers.iter().any(|server| !server.webhooks.is_empty());
+        if !has_webhooks { return; }
+
+        // Subscribing here instead of in the thread makes sure no event is broadcast before the thread is ready
+        let event_receiver = get_event_broadcaster().subscribe();
+        let shutdown_notify = self.shutdown_notify.clone();
+        let _ = self.thread_webhooks.get_or_init(move || {
+            return tokio::spawn(boot_thread_webhooks(global_webhooks, secret, event_receiver, shutdown_notify).instrument(tracing::info_span!("webhooks")));
+        });
+    }
+
+    /// Sends the events already broadcast and waits for deliveries still being retried. Whatever isn't done
+    /// after the timeout gets dropped
+    pub async fn shutdown(&mut self) {
+        let thread_webhooks = match self.thread_webhooks.take() {
+            Some(thread_webhooks) => { thread_webhooks }
+            None => { return; }
+        };
+
+        self.shutdown_notify.notify_one();
+        let abort_handle = thread_webhooks.abort_handle();
+        if tokio::time::timeout(Duration::from_secs(SHUTDOWN_TIMEOUT_SECS), thread_webhooks).await.is_err() {
+            tracing::warn!("Webhooks didn't finish before timeout. Dropping pending deliveries");
+            abort_handle.abort();
+        }
+    }
+}
+
+async fn boot_thread_webhooks(global_webhooks: Vec<String>, secret: String, mut event_receiver: tokio::sync::broadcast::Receiver<CsctrlServerEvent>, shutdown_notify: Arc<Notify>) {
+    tracing::debug!("Webhooks task created");
+    let client = build_https_client();
+    let delivery_log_path = get_delivery_log_path();
+    // Deliveries run concurrently so a slow receiver doesn't hold back the others, and are kept here so shutdown can wait for them
+    let mut deliveries = JoinSet::new();
+
+    loop {
+        let server_event = tokio::select! {
+            received_event = event_receiver.recv() => {
+                match received_event {
+                    Ok(server_event) => { server_event }
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped_events)) => {
+                        tracing::warn!("Webhooks fell behind and skipped {} events", skipped_events);
+                        continue;
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => { break; }
+                }
+            }
+            Some(_) = deliveries.join_next() => { continue; }
+            _ = shutdown_notify.notified() => { break; }
+        };
+        queue_deliveries(&mut deliveries, &client, &delivery_log_path, &global_webhooks, &secret, server_event);
+    }
+
+    while let Ok(server_event) = event_receiver.try_recv() {
+        queue_deliveries(&mut deliveries, &client, &delivery_log_path, &global_webhooks, &secret, server_event);
+    }
+    if !deliveries.is_empty() {
+        tracing::info!("Waiting for {} webhook deliveries", deliveries.len());
+    }
+    while deliveries.join_next().await.is_some() {}
+
+    tracing::debug!("Thread shutting down");
+}
+
+fn queue_deliveries(deliveries: &mut JoinSet<()>, client: &HttpsClient, delivery_log_path: &Path, global_webhooks: &[String], secret: &str, server_event: CsctrlServerEvent) {
+    let event_name = match get_lifecycle_event_name(&server_event.event) {
+        Some(event_name) => { event_name }
+        None => { return; }
+    };
+
+    let mut webhook_urls = global_webhooks.to_vec();
+    let data = match get_data().read().unwrap().servers.get(&server_event.server_address) {
+        Some(server_data) => {
+            webhook_urls.extend(server_data.config.webhooks.clone());
+            get_event_data(&server_event.event, server_data)
+        }
+        None => { return; }
+    };
+    if webhook_urls.is_empty() { return; }
+
+    let timestamp = get_unix_timestamp_secs();
+    let payload = WebhookPayload {
+        event: event_name.to_string(),
+        delivery_id: format!("{}-{}", timestamp, rand::random::<u32>()),
+        server_address: server_event.server_address.clone(),
+        timestamp,
+        data,
+    };
+    let body = match serde_json::to_string(&payload) {
+        Ok(body) => { body }
+        Err(error) => {
+            tracing::error!("Can't serialize webhook payload for event '{}'. Error: {}", event_name, error);
+            return;
+        }
+    };
+    let signature = sign_payload(secret, &body);
+
+    for webhook_url in webhook_urls {
+        deliveries.spawn(deliver_webhook(client.clone(), WebhookDelivery {
+            url: webhook_url,
+            event_name: payload.event.clone(),
+            delivery_id: payload.delivery_id.clone(),
+            body: body.clone(),
+            signature: signature.clone(),
+            delivery_log_path: delivery_log_path.to_path_buf(),
+        }).in_current_span());
+    }
+}
+
+fn build_https_client() -> HttpsClient {
+    let https_connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_webpki_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    return hyper::Client::builder().build(https_connector);
+}
+
+/// Only match lifecycle changes are sent as webhooks, the rest stays on the event stream
+fn get_lifecycle_event_name(event: &CsctrlEvent) -> Option<&'static str> {
+    return match event {
+        CsctrlEvent::StatusChange { status, previous_status } => {
+            match status {
+                CsctrlMatchStatus::PreMatchWarmup => { Some("match_loaded") }
+                CsctrlMatchStatus::Paused => { Some("match_paused") }
+                CsctrlMatchStatus::Live if *previous_status == Some(CsctrlMatchStatus::Paused) => { Some("match_unpaused") }
+                CsctrlMatchStatus::Live => { Some("match_live") }
+                _ => { None }
+            }
+        }
+        CsctrlEvent::Halftime { .. } => { Some("match_half") }
+        CsctrlEvent::MatchFinished { .. } => { Some("match_finished") }
+        _ => { None }
+    };
+}
+
+fn get_event_data(event: &CsctrlEvent, server_data: &crate::csctrl::types::CsctrlDataServer) -> serde_json::Value {
+    if let CsctrlEvent::MatchFinished { result } = event {
+        return serde_json::to_value(result).unwrap_or_default();
+    }
+
+    return serde_json::json!({
+        "server_name": server_data.config.name,
+        "status": server_data.status,
+        "map": server_data.map,
+        "round": server_data.rounds_played,
+        "team_ct": { "name": server_data.team_ct.name, "score": server_data.team_ct.score },
+        "team_t": { "name": server_data.team_t.name, "score": server_data.team_t.score },
+    });
+}
+
+/// Receivers check the 'X-Csctrl-Signature' header by computing the HMAC-SHA256 of the raw body with the config secret
+fn sign_payload(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    return format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+}
+
+/// Retries with an exponential backoff until the receiver answers with a success status
+async fn deliver_webhook(client: HttpsClient, delivery: WebhookDelivery) {
+    for attempt in 1..=WEBHOOK_MAX_ATTEMPTS {
+        let request = match hyper::Request::post(&delivery.url)
+            .header("content-type", "application/json")
+            .header("x-csctrl-event", &delivery.event_name)
+            .header("x-csctrl-delivery", &delivery.delivery_id)
+            .header("x-csctrl-signature", &delivery.signature)
+            .body(hyper::Body::from(delivery.body.clone())) {
+            Ok(request) => { request }
+            Err(error) => {
+                write_delivery_log(&delivery, attempt, None, Some(error.to_string()), false);
+                return;
+            }
+        };
+
+        let (status_code, error) = match tokio::time::timeout(Duration::from_secs(WEBHOOK_REQUEST_TIMEOUT_SECS), client.request(request)).await {
+            Ok(Ok(response)) => { (Some(response.status()), None) }
+            Ok(Err(error)) => { (None, Some(error.to_string())) }
+            Err(_) => { (None, Some("Request timed out".to_string())) }
+        };
+
+        let is_delivered = status_code.is_some_and(|status_code| status_code.is_success());
+        write_delivery_log(&delivery, attempt, status_code.map(|status_code| status_code.as_u16()), error, is_delivered);
+        if is_delivered { return; }
+
+        if attempt < WEBHOOK_MAX_ATTEMPTS {
+            tokio::time::sleep(Duration::from_secs(2u64.pow(attempt - 1))).await;
+        }
+    }
+
+    tracing::error!("Webhook '{}' for event '{}' failed after {} attempts", delivery.url, delivery.event_name, WEBHOOK_MAX_ATTEMPTS);
+}
+
+/// Every attempt is appended as a JSON line to 'logs/webhooks.log'
+fn get_delivery_log_path() -> PathBuf {
+    let mut delivery_log_path = get_csctrl_config_file_path();
+    delivery_log_path.pop();
+    delivery_log_path.push("logs/webhooks.log");
+    return delivery_log_path;
+}
+
+fn write_delivery_log(delivery: &WebhookDelivery, attempt: u32, status_code: Option<u16>, error: Option<String>, is_delivered: bool) {
+    let delivery_log = WebhookDeliveryLog {
+        timestamp: get_unix_timestamp_secs(),
+        delivery_id: &delivery.delivery_id,
+        event: &delivery.event_name,
+        url: &delivery.url,
+        attempt,
+        status_code,
+        error,
+        is_delivered,
+    };
+
+    let mut file = match std::fs::OpenOptions::new().append(true).create(true).open(&delivery.delivery_log_path) {
+        Ok(file) => { file }
+        Err(error) => {
+            tracing::error!("Can't open webhook delivery log '{}'. Error: {}", delivery.delivery_log_path.to_str().unwrap(), error);
+            return;
+        }
+    };
+
+    if let Ok(delivery_log_line) = serde_json::to_string(&delivery_log) {
+        let _ = writeln!(file, "{}", delivery_log_line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+
+    const SECRET: &str = "webhook-test-secret";
+
+    #[derive(Clone)]
+    struct StandIn {
+        received_requests: Arc<Mutex<Vec<(Instant, HeaderMap, String)>>>,
+        status_codes: Arc<Mutex<Vec<StatusCode>>>,
+    }
+
+    async fn receive_webhook(State(stand_in): State<StandIn>, headers: HeaderMap, body: String) -> StatusCode {
+        stand_in.received_requests.lock().unwrap().push((Instant::now(), headers, body));
+        let mut status_codes = stand_in.status_codes.lock().unwrap();
+        if status_codes.len() > 1 { return status_codes.remove(0); }
+        return status_codes[0];
+    }
+
+    /// Binds a local receiver that answers with the given status codes in order and keeps repeating the last one
+    fn start_stand_in(status_codes: Vec<StatusCode>) -> (String, StandIn) {
+        let stand_in = StandIn {
+            received_requests: Arc::new(Mutex::new(vec![])),
+            status_codes: Arc::new(Mutex::new(status_codes)),
+        };
+        let router = axum::Router::new()
+            .route("/webhook", axum::routing::post(receive_webhook))
+            .with_state(stand_in.clone());
+        let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(router.into_make_service());
+        let url = format!("http://{}/webhook", server.local_addr());
+        tokio::spawn(server);
+        return (url, stand_in);
+    }
+
+    fn create_delivery(url: &str, body: &str) -> WebhookDelivery {
+        return WebhookDelivery {
+            url: url.to_string(),
+            event_name: "match_live".to_string(),
+            delivery_id: format!("1700000000-{}", rand::random::<u32>()),
+            body: body.to_string(),
+            signature: sign_payload(SECRET, body),
+            delivery_log_path: std::env::temp_dir().join(format!("csctrl_webhooks_test_{}.log", rand::random::<u32>())),
+        };
+    }
+
+    fn read_delivery_log(delivery_log_path: &PathBuf) -> Vec<serde_json::Value> {
+        let delivery_log = std::fs::read_to_string(delivery_log_path).unwrap();
+        let _ = std::fs::remove_file(delivery_log_path);
+        return delivery_log.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    }
+
+    #[test]
+    fn signs_payload_with_hmac_sha256() {
+        assert_eq!(sign_payload("key", "The quick brown fox jumps over the lazy dog"), "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_payload_and_logs_it() {
+        let (url, stand_in) = start_stand_in(vec![StatusCode::OK]);
+        let body = r#"{"event":"match_live","server_address":"127.0.0.1:27015"}"#;
+        let delivery = create_delivery(&url, body);
+        let delivery_id = delivery.delivery_id.clone();
+        let delivery_log_path = delivery.delivery_log_path.clone();
+
+        deliver_webhook(build_https_client(), delivery).await;
+
+        let received_requests = stand_in.received_requests.lock().unwrap().clone();
+        assert_eq!(received_requests.len(), 1);
+        let (_, headers, received_body) = &received_requests[0];
+        assert_eq!(received_body, body);
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(headers["x-csctrl-event"], "match_live");
+        assert_eq!(headers["x-csctrl-delivery"], delivery_id.as_str());
+
+        // Receivers check the signature against the raw body they got with the shared secret
+        let signature = headers["x-csctrl-signature"].to_str().unwrap().strip_prefix("sha256=").unwrap();
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(received_body.as_bytes());
+        assert!(mac.verify_slice(&hex::decode(signature).unwrap()).is_ok());
+
+        let delivery_log = read_delivery_log(&delivery_log_path);
+        assert_eq!(delivery_log.len(), 1);
+        assert_eq!(delivery_log[0]["delivery_id"], delivery_id.as_str());
+        assert_eq!(delivery_log[0]["event"], "match_live");
+        assert_eq!(delivery_log[0]["url"], url.as_str());
+        assert_eq!(delivery_log[0]["attempt"], 1);
+        assert_eq!(delivery_log[0]["status_code"], 200);
+        assert_eq!(delivery_log[0]["error"], serde_json::Value::Null);
+        assert_eq!(delivery_log[0]["is_delivered"], true);
+    }
+
+    #[tokio::test]
+    async fn retries_with_backoff_after_server_error() {
+        let (url, stand_in) = start_stand_in(vec![StatusCode::INTERNAL_SERVER_ERROR, StatusCode::INTERNAL_SERVER_ERROR, StatusCode::OK]);
+        let body = r#"{"event":"match_live"}"#;
+        let delivery = create_delivery(&url, body);
+        let delivery_log_path = delivery.delivery_log_path.clone();
+
+        deliver_webhook(build_https_client(), delivery).await;
+
+        let received_requests = stand_in.received_requests.lock().unwrap().clone();
+        assert_eq!(received_requests.len(), 3);
+        assert!(received_requests.iter().all(|(_, _, received_body)| received_body == body));
+        // Waits 1s after the first failure and 2s after the second
+        assert!(received_requests[1].0 - received_requests[0].0 >= Duration::from_secs(1));
+        assert!(received_requests[2].0 - received_requests[1].0 >= Duration::from_secs(2));
+
+        let delivery_log = read_delivery_log(&delivery_log_path);
+        let logged_attempts: Vec<(u64, u64, bool)> = delivery_log.iter()
+            .map(|log_line| (log_line["attempt"].as_u64().unwrap(), log_line["status_code"].as_u64().unwrap(), log_line["is_delivered"].as_bool().unwrap()))
+            .collect();
+        assert_eq!(logged_attempts, vec![(1, 500, false), (2, 500, false), (3, 200, true)]);
+    }
+
+    #[tokio::test]
+    async fn logs_connection_errors() {
+        // Nothing listens on the port once the listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+        drop(listener);
+
+        let delivery = create_delivery(&url, "{}");
+        let delivery_log_path = delivery.delivery_log_path.clone();
+        let client = build_https_client();
+        let delivery_task = tokio::spawn(deliver_webhook(client, delivery));
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        delivery_task.abort();
+
+        let delivery_log = read_delivery_log(&delivery_log_path);
+        assert_eq!(delivery_log[0]["attempt"], 1);
+        assert_eq!(delivery_log[0]["status_code"], serde_json::Value::Null);
+        assert!(delivery_log[0]["error"].is_string());
+        assert_eq!(delivery_log[0]["is_delivered"], false);
+    }
+}
\ No newline at end of file
@@ -1,16 +1,66 @@
 use std::cell::OnceCell;
-use std::thread::JoinHandle;
-use axum::Router;
-use crate::csctrl::types::CsctrlConfig;
+use std::sync::Arc;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::time::Duration;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use tokio_stream::{Stream, StreamExt};
+use tokio_stream::wrappers::BroadcastStream;
+use axum::{Json, Router};
+use crate::commands::base::CommandResult;
+use crate::csctrl::csctrl::{get_data, get_event_broadcaster, get_message_sender, SHUTDOWN_TIMEOUT_SECS};
+use crate::csctrl::types::{CsctrlEvent, CsctrlMessage, CsctrlServerEvent, CsctrlConfig, CsctrlDataConnection, CsctrlDataPlayer, CsctrlDataPlayerStats, CsctrlDataStatusPlayer};
+
+#[derive(Clone)]
+struct CslogState {
+    secret: String,
+}
+
+#[derive(Clone)]
+struct RestApiState {
+    api_token: String,
+}
+
+const COMMAND_RESPONSE_TIMEOUT_SECS: u64 = 15;
+
+#[derive(serde::Deserialize)]
+struct CommandRequest {
+    command: String,
+}
+
+#[derive(serde::Serialize)]
+struct CommandResponse {
+    success: bool,
+    output: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct ServerPlayersResponse<'a> {
+    team_ct: &'a Vec<CsctrlDataPlayer>,
+    team_t: &'a Vec<CsctrlDataPlayer>,
+    connections: &'a HashMap<String, CsctrlDataConnection>,
+    player_stats: &'a HashMap<String, CsctrlDataPlayerStats>,
+    status: &'a Vec<CsctrlDataStatusPlayer>,
+}
 
 pub struct Webserver {
-    thread_restapi: OnceCell<JoinHandle<()>>
+    thread_restapi: OnceCell<JoinHandle<()>>,
+    shutdown_notify: Arc<Notify>,
 }
 
 impl Webserver {
     pub fn webserver() -> Webserver {
         Webserver {
             thread_restapi: OnceCell::new(),
+            shutdown_notify: Arc::new(Notify::new()),
         }
     }
 
@@ -20,47 +70,247 @@ impl Webserver {
 
     fn start_rest_api(&self, csctrl_config: &CsctrlConfig) {
         let ip_port = &csctrl_config.rest_api_address;
-        let receive_cslog_path = &csctrl_config.cs_listen_path;
+        // The secret travels as the last path segment since CS2 can't attach custom headers to its log requests
+        let receive_cslog_path = format!("{}/:secret", csctrl_config.cs_listen_path.trim_end_matches('/'));
+        let cslog_state = CslogState { secret: csctrl_config.secret.clone() };
+        let rest_api_state = RestApiState { api_token: csctrl_config.api_token.clone() };
+        if rest_api_state.api_token.is_empty() {
+            tracing::warn!("No api_token is configured. Every rest api request will be rejected");
+        }
 
-        let api = axum::Router::new()
-            .route(receive_cslog_path, axum::routing::post(receive_cslog));
+        let cslog_router = axum::Router::new()
+            .route(&receive_cslog_path, axum::routing::post(receive_cslog))
+            .with_state(cslog_state);
+
+        let rest_api_router = axum::Router::new()
+            .route("/api/servers", axum::routing::get(get_servers))
+            .route("/api/servers/:address", axum::routing::get(get_server))
+            .route("/api/servers/:address/players", axum::routing::get(get_server_players))
+            .route("/api/servers/:address/commands", axum::routing::post(post_server_command))
+            .route("/api/servers/:address/events", axum::routing::get(get_server_events))
+            .route("/api/commands", axum::routing::post(post_command))
+            .route("/api/events", axum::routing::get(get_events))
+            .route_layer(axum::middleware::from_fn_with_state(rest_api_state, authenticate_rest_api));
+
+        let api = cslog_router.merge(rest_api_router);
 
         let _ = &self.prepare_thread_restapi(ip_port.to_string(), api);
     }
 
     fn prepare_thread_restapi(&self, address: String, router: Router) {
+        let shutdown_notify = self.shutdown_notify.clone();
         let _ = self.thread_restapi.get_or_init(move || {
-            return std::thread::Builder::new().name("[Webserver]".to_string()).spawn(move || {
-                tracing::debug!("Thread created");
-                boot_thread_restapi(address.to_string(), router);
-            }).unwrap();
+            return tokio::spawn(boot_thread_restapi(address, router, shutdown_notify).instrument(tracing::info_span!("webserver")));
         });
     }
 
-    pub fn shutdown(&self) {
+    /// Stops accepting connections and waits for in-flight requests. Event streams never finish on their own,
+    /// so whatever is still open after the timeout gets dropped
+    pub async fn shutdown(&mut self) {
+        let thread_restapi = match self.thread_restapi.take() {
+            Some(thread_restapi) => { thread_restapi }
+            None => { return; }
+        };
 
+        self.shutdown_notify.notify_one();
+        let abort_handle = thread_restapi.abort_handle();
+        if tokio::time::timeout(Duration::from_secs(SHUTDOWN_TIMEOUT_SECS), thread_restapi).await.is_err() {
+            tracing::warn!("Rest api didn't stop before timeout. Dropping open connections");
+            abort_handle.abort();
+        }
     }
 }
 
-#[tokio::main]
-async fn boot_thread_restapi(address: String, router: Router) {
+async fn boot_thread_restapi(address: String, router: Router, shutdown_notify: Arc<Notify>) {
+    tracing::debug!("Rest api task created");
     axum::Server::bind(&address.parse().unwrap())
-        .serve(router.into_make_service()).await.unwrap();
+        .serve(router.into_make_service())
+        .with_graceful_shutdown(async move { shutdown_notify.notified().await })
+        .await.unwrap();
+    tracing::debug!("Rest api stopped");
 }
 
-async fn receive_cslog(request: axum::http::Request<axum::body::Body>) {
-    let cloned_request_headers = request.headers().clone();
-    let request_address = cloned_request_headers.get("x-server-addr");
-    if request_address.is_none() { return; }
+async fn receive_cslog(State(cslog_state): State<CslogState>, Path(secret): Path<String>, request: axum::http::Request<axum::body::Body>) -> StatusCode {
+    if !is_secret_valid(&cslog_state.secret, &secret) {
+        tracing::warn!("Rejected CS2 log with an invalid secret");
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let request_address = match request.headers().get("x-server-addr").map(|header| header.to_str()) {
+        Some(Ok(request_address)) => { request_address.to_string() }
+        _ => { return StatusCode::BAD_REQUEST; }
+    };
 
-    let request_body = std::str::from_utf8(&hyper::body::to_bytes(request.into_body())
-        .await.unwrap()).unwrap().to_string();
+    let is_registered_server = get_data().read().unwrap().servers.contains_key(&request_address);
+    if !is_registered_server {
+        tracing::warn!("Rejected CS2 log from unregistered server '{}'", request_address);
+        return StatusCode::FORBIDDEN;
+    }
 
-    let mut weblog_message = format!("{:?}{}{}", request_address.unwrap(), crate::csctrl::csctrl::FORMAT_SEPARATOR, request_body);
+    let mut request_body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(request_body) => { String::from_utf8_lossy(&request_body).to_string() }
+        Err(_) => { return StatusCode::BAD_REQUEST; }
+    };
 
     // Remove new line character from the end
-    weblog_message.pop();
+    request_body.pop();
+
+    tracing::trace!("Received CS2 log from '{}'. Content:\n{}", request_address, request_body);
+    let _ = get_message_sender().send(CsctrlMessage::Weblog { server_address: request_address, body: request_body });
+    return StatusCode::OK;
+}
+
+/// Rest api requests carry the config api token as a bearer token. The log secret is handed to every CS2 server, so it can't grant api access
+async fn authenticate_rest_api<B>(State(rest_api_state): State<RestApiState>, request: axum::http::Request<B>, next: Next<B>) -> Response {
+    let received_token = request.headers().get(axum::http::header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .unwrap_or("");
+
+    if !is_secret_valid(&rest_api_state.api_token, received_token) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    return next.run(request).await;
+}
+
+async fn get_servers() -> Result<Json<serde_json::Value>, StatusCode> {
+    let data = get_data().read().unwrap();
+    return match serde_json::to_value(&*data) {
+        Ok(servers) => { Ok(Json(servers)) }
+        Err(error) => {
+            tracing::error!("Can't serialize server data. Error: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    };
+}
+
+async fn get_server(Path(address): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let data = get_data().read().unwrap();
+    let server_data = match data.servers.get(&address) {
+        Some(server_data) => { server_data }
+        None => { return Err(StatusCode::NOT_FOUND); }
+    };
+
+    return match serde_json::to_value(server_data) {
+        Ok(server) => { Ok(Json(server)) }
+        Err(error) => {
+            tracing::error!("Can't serialize data of server '{}'. Error: {}", address, error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    };
+}
+
+async fn get_server_players(Path(address): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let data = get_data().read().unwrap();
+    let server_data = match data.servers.get(&address) {
+        Some(server_data) => { server_data }
+        None => { return Err(StatusCode::NOT_FOUND); }
+    };
+
+    let players = ServerPlayersResponse {
+        team_ct: &server_data.team_ct.players,
+        team_t: &server_data.team_t.players,
+        connections: &server_data.connections,
+        player_stats: &server_data.player_stats,
+        status: &server_data.server_info.players,
+    };
+    return match serde_json::to_value(players) {
+        Ok(players) => { Ok(Json(players)) }
+        Err(error) => {
+            tracing::error!("Can't serialize players of server '{}'. Error: {}", address, error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    };
+}
+
+async fn get_events() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    return stream_events(None);
+}
+
+async fn get_server_events(Path(address): Path<String>) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    if !get_data().read().unwrap().servers.contains_key(&address) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    return Ok(stream_events(Some(address)));
+}
+
+/// Starts with a snapshot of every matching server and then follows with events as they are broadcast.
+/// Subscribing before taking the snapshot means no event is lost in between
+fn stream_events(address_filter: Option<String>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let event_receiver = get_event_broadcaster().subscribe();
+
+    let snapshot_events: Vec<CsctrlServerEvent> = get_data().read().unwrap().servers.values()
+        .filter(|server_data| address_filter.as_ref().is_none_or(|address| *address == server_data.config.address))
+        .map(|server_data| CsctrlServerEvent {
+            server_address: server_data.config.address.clone(),
+            event: CsctrlEvent::Snapshot { server: Box::new(server_data.clone()) },
+        })
+        .collect();
+
+    // Clients that fall too far behind skip the events they missed instead of closing the stream
+    let live_events = BroadcastStream::new(event_receiver)
+        .filter_map(|server_event| server_event.ok())
+        .filter(move |server_event| address_filter.as_ref().is_none_or(|address| *address == server_event.server_address));
+
+    let event_stream = tokio_stream::iter(snapshot_events)
+        .chain(live_events)
+        .filter_map(|server_event| to_sse_event(&server_event))
+        .map(Ok);
+
+    return Sse::new(event_stream).keep_alive(KeepAlive::default());
+}
+
+fn to_sse_event(server_event: &CsctrlServerEvent) -> Option<Event> {
+    return match Event::default().event(server_event.event.get_name()).json_data(server_event) {
+        Ok(event) => { Some(event) }
+        Err(error) => {
+            tracing::error!("Can't serialize event for server '{}'. Error: {}", server_event.server_address, error);
+            None
+        }
+    };
+}
+
+async fn post_server_command(Path(address): Path<String>, Json(command_request): Json<CommandRequest>) -> (StatusCode, Json<CommandResponse>) {
+    if !get_data().read().unwrap().servers.contains_key(&address) {
+        return command_response(StatusCode::NOT_FOUND, Err(format!("Can't find a server with address '{}'", address)));
+    }
+    return execute_command(address, command_request.command).await;
+}
+
+/// Runs commands that don't target a specific server, like 'csctrl.generate.server'
+async fn post_command(Json(command_request): Json<CommandRequest>) -> (StatusCode, Json<CommandResponse>) {
+    return execute_command("".to_string(), command_request.command).await;
+}
+
+/// Queues the command for the main thread and waits for it, or the rcon response it triggers, to finish
+async fn execute_command(target_address: String, command: String) -> (StatusCode, Json<CommandResponse>) {
+    let (responder, receiver) = tokio::sync::oneshot::channel();
+    let _ = get_message_sender().send(CsctrlMessage::Command {
+        target_address,
+        command,
+        responder: Some(responder),
+    });
+
+    return match tokio::time::timeout(Duration::from_secs(COMMAND_RESPONSE_TIMEOUT_SECS), receiver).await {
+        Ok(Ok(command_result)) => {
+            let status_code = if command_result.is_ok() { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+            command_response(status_code, command_result)
+        }
+        Ok(Err(_)) => { command_response(StatusCode::INTERNAL_SERVER_ERROR, Err("Command was dropped before it finished".to_string())) }
+        Err(_) => { command_response(StatusCode::GATEWAY_TIMEOUT, Err("Command didn't finish before timeout".to_string())) }
+    };
+}
+
+fn command_response(status_code: StatusCode, command_result: CommandResult) -> (StatusCode, Json<CommandResponse>) {
+    let command_response = match command_result {
+        Ok(output) => { CommandResponse { success: true, output: Some(output), error: None } }
+        Err(error) => { CommandResponse { success: false, output: None, error: Some(error) } }
+    };
+    return (status_code, Json(command_response));
+}
 
-    tracing::trace!("Received CS2 log. Content:\n{}", weblog_message);
-    crate::csctrl::csctrl::get_weblogs_messenger().write().unwrap().push_back(weblog_message);
+/// Compares every byte so the response time doesn't reveal how much of the secret was guessed
+fn is_secret_valid(secret: &str, received_secret: &str) -> bool {
+    if secret.is_empty() || secret.len() != received_secret.len() { return false; }
+    return secret.bytes().zip(received_secret.bytes()).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0;
 }
\ No newline at end of file